@@ -0,0 +1,108 @@
+// Generated from fed_schema.rs by `fed2 typescript`. Don't edit by hand.
+
+export type Advancement = { runner: PlayerDesc, to_base: Base, };
+
+export type AdvancementFlavor = "to" | "advances_to";
+
+export type BallFlavor = "doesnt_blink" | "just_misses" | "lays_off_outside" | "looks_at_ball_outside" | "misses_big_time" | "stumbles" | "throws_outside" | { "adjective": PitchAdjective } | "ball_comma" | "extremely_outside" | "just_outside" | "way_outside" | "ball_period" | "misses_the_zone" | "does_not_chase";
+
+export type Base = "first" | "second" | "third";
+
+export type CatchAdjective = "cool" | "decent" | "diving" | "fine" | "good" | "lazy" | "nice" | "poor" | "satisfactory" | "simple" | "solid";
+
+export type Contact = { batter: PlayerDesc, location: FieldLocation | null, flavor: ContactFlavor, };
+
+export type ContactAdjective = "decent" | "depressing" | "hard" | "sad" | "solid" | "strong" | "weak";
+
+export type ContactFlavor = { "named_with_sound": { sound_effect: SoundEffect, verb: ContactVerb, } } | { "named": { verb: ContactVerb, pitch_descriptor: PitchDescriptor, } } | { "adjective": { adjective: ContactAdjective, } };
+
+export type ContactVerb = "bats" | "chops" | "clips" | "drags" | "dribbles" | "hits" | "knocks" | "nudges" | "pokes" | "punches" | "pushes" | "rolls" | "slaps" | "smacks" | "sputters" | "swats" | "taps" | "thumps" | "trickles" | "whacks";
+
+export type Event = { "type": "play_ball" } | { "type": "batter_up", batter: PlayerDesc, 
+/**
+ * `None` if chronicler didn't have a pitcher when the batter stepped up
+ */
+pitcher: PlayerDesc | null, } | { "type": "ball", flavor: BallFlavor, } | { "type": "strike", flavor: StrikeFlavor, } | { "type": "flyout", contact: Contact, defender: PlayerDesc, flavor: FlyoutFlavor, } | { "type": "ground_out", contact: Contact, fielding: Fielding, flavor: GroundoutFlavor, advancements: Array<RunnerAdvancementDesc>, } | { "type": "strikeout", batter: PlayerDesc, flavor: StrikeoutFlavor, } | { "type": "foul", flavor: FoulFlavor, } | { "type": "home_run", contact: Contact, 
+/**
+ * Runners who were on base, and scored ahead of the batter
+ */
+scores: Array<PlayerDesc>, } | { "type": "hit", contact: Contact, fielding: MaybeFailedFielding, hit_type: HitType, flavor: HitFlavor, advancements: Array<Advancement>, scores: Array<PlayerDesc>, } | { "type": "walk", batter: PlayerDesc, flavor: WalkFlavor, } | { "type": "end_of_half_inning", top_of_inning: boolean, inning: number, } | { "type": "fielders_choice", contact: Contact, fielding: Fielding, runner_out: RunnerDesc, };
+
+export type FailedFielding = { defender: PlayerDesc, flavor: FailedFieldingFlavor, };
+
+export type FailedFieldingFlavor = "bobbles_it" | "cant_collect_it" | "cant_corral_it" | "cant_field_it" | "cant_get_it" | "cant_make_the_catch" | "cant_scoop_it" | "cant_secure_it" | "drops_it" | "is_late_getting_there" | "just_misses_the_catch" | "loses_it";
+
+/**
+ * An `Event` along with which game it's from and which chronicler updates it was parsed from.
+ * This is the unit Fed stores and hands on.
+ */
+export type FedEvent = { 
+/**
+ * Always `SCHEMA_VERSION` for events written by this build
+ */
+schema_version: number, 
+/**
+ * Derived from the game id and `first_display_order`, so parsing the same game again gives
+ * the same ids
+ */
+id: string, game_id: string, 
+/**
+ * The event's position in its game, starting from 0
+ */
+sequence: number, 
+/**
+ * Chronicler timestamp of the event's first update
+ */
+timestamp: string, 
+/**
+ * Display time of the event's first update
+ */
+display_time: string, 
+/**
+ * Display order of the event's first update
+ */
+first_display_order: number, 
+/**
+ * Display order of the event's last update
+ */
+last_display_order: number, event: Event, };
+
+export type FieldLocation = "infield" | "left_field" | "deep_left_field" | "center_field" | "deep_center_field" | "right_field" | "deep_right_field" | "wall";
+
+export type Fielding = { defender: PlayerDesc, flavor: FieldingFlavor, };
+
+export type FieldingFlavor = "charges_for_it" | "collects_it" | "corrals_it" | "dashes_for_it" | "dives_for_it" | "fields_it" | "gets_in_front_of_it" | "gets_it" | "goes_for_it" | "has_a_bead_on_it" | "is_there_to_collect_it" | "is_there_to_corral_it" | "is_there_to_field_it" | "is_there_to_get_it" | "is_there_to_scoop_it" | "is_there_to_secure_it" | "lurches_for_it" | "races_for_it" | "races_in" | "races_toward_it" | "reaches_for_it" | "runs_for_it" | "scoops_it" | "secures_it" | "tracks_it_down" | "tries_for_it";
+
+export type FlyoutFlavor = "fly_out_to" | "is_right_there" | "makes_catch" | { "makes_catch_with_adjective": CatchAdjective };
+
+export type FoulFlavor = "foul_ball" | "foul_tip" | "fouls_it_back" | "fouls_it_off";
+
+export type GroundoutFlavor = "ground_out_to" | "hits_a_groundout" | "forced_out_at_first";
+
+export type HitFlavor = "hits" | "is_on_with";
+
+export type HitType = "single" | "double" | "triple";
+
+export type MaybeFailedFielding = { "fielding": Fielding } | { "failed_fielding": FailedFielding };
+
+export type PitchAdjective = "auspicious" | "average" | "disgusting" | "dominant" | "favorable" | "horrible" | "marvelous" | "overpowering" | "potent" | "powerful" | "revolting" | "well_located" | "well_placed";
+
+export type PitchDescriptor = "it" | "one" | "the_ball" | "the_pitch";
+
+export type PlayerDesc = { id: string, name: string, };
+
+export type RunnerAdvancement = "none" | { "advanced": [Base, AdvancementFlavor] } | "scored";
+
+export type RunnerAdvancementDesc = { runner: RunnerDesc, advancement: RunnerAdvancement, };
+
+export type RunnerDesc = { id: string, name: string, base: number, };
+
+export type SoundEffect = "bam" | "boom" | "crack" | "smack" | "smash" | "thwack" | "wham";
+
+export type StrikeFlavor = "none" | "looking" | "swinging" | "throws_a_strike" | "caught_looking" | "chases" | "guesses_wrong" | { "adjective_swing": SwingAdjective } | "drops_it_in" | "swings_weakly";
+
+export type StrikeoutFlavor = "named_both" | "named_batter";
+
+export type SwingAdjective = "pathetic" | "poor" | "sad" | "weak";
+
+export type WalkFlavor = "ball4" | "draws_walk" | "earns_walk";
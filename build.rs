@@ -0,0 +1,22 @@
+use prost::Message as _;
+use protobuf::Message as _;
+
+/// Generates the messages in `fed2::proto` from `proto/fed.proto`. It's parsed with protobuf-parse's
+/// pure Rust parser so that building doesn't need protoc.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-changed=proto/fed.proto");
+
+    let parsed = protobuf_parse::Parser::new()
+        .pure()
+        .include("proto")
+        .input("proto/fed.proto")
+        .parse_and_typecheck()?;
+    // Includes the well-known types fed.proto imports, which prost needs to see to generate it.
+    // protobuf-parse and prost each have their own descriptor types, so go from one to the other
+    // through the wire format.
+    let mut file_descriptor_set = protobuf::descriptor::FileDescriptorSet::new();
+    file_descriptor_set.file = parsed.file_descriptors;
+    let file_descriptor_set = prost_types::FileDescriptorSet::decode(file_descriptor_set.write_to_bytes()?.as_slice())?;
+    prost_build::Config::new().compile_fds(file_descriptor_set)?;
+    Ok(())
+}
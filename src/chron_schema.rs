@@ -1,10 +1,16 @@
+use std::collections::{BTreeMap, HashSet};
 use std::fmt::{Display, Formatter};
+use std::sync::{LazyLock, Mutex};
+use anyhow::anyhow;
 use chrono::{DateTime, Utc};
-use serde::Deserialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use tracing::warn;
 use uuid::Uuid;
 use crate::fed_schema::Base;
 
-#[derive(Debug, Copy, Clone, Default, Deserialize, PartialEq)]
+#[derive(Debug, Copy, Clone, Default, Deserialize, PartialEq, Serialize)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum TeamAtBat {
     #[default]
@@ -12,12 +18,24 @@ pub enum TeamAtBat {
     Home,
 }
 
-#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema, TS)]
 #[serde(rename_all = "camelCase")]
-#[serde(deny_unknown_fields)]
 pub struct PlayerDesc {
     pub id: Uuid,
     pub name: String,
+    /// Players are part of Fed events, which already carry these under their own `extra`, so
+    /// they're only kept here and never written back out
+    #[serde(flatten, skip_serializing)]
+    #[schemars(skip)]
+    #[ts(skip)]
+    pub extra: ExtraFields,
+}
+
+// Two descriptions of the same player are equal however many unknown fields came with them
+impl PartialEq for PlayerDesc {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id && self.name == other.name
+    }
 }
 
 impl Display for PlayerDesc {
@@ -26,13 +44,24 @@ impl Display for PlayerDesc {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema, TS)]
 #[serde(rename_all = "camelCase")]
-#[serde(deny_unknown_fields)]
 pub struct RunnerDesc {
     pub id: Uuid,
     pub name: String,
+    #[ts(type = "number")]
     pub base: i64,
+    /// Never written back out, the same as `PlayerDesc::extra`
+    #[serde(flatten, skip_serializing)]
+    #[schemars(skip)]
+    #[ts(skip)]
+    pub extra: ExtraFields,
+}
+
+impl PartialEq for RunnerDesc {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id && self.name == other.name && self.base == other.base
+    }
 }
 
 impl Display for RunnerDesc {
@@ -41,9 +70,11 @@ impl Display for RunnerDesc {
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// Fields chronicler sent that the struct they're in doesn't know about
+pub type ExtraFields = BTreeMap<String, serde_json::Value>;
+
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
-#[serde(deny_unknown_fields)]
 pub struct StateDelta {
     #[serde(default, skip_serializing_if = "Option::is_none", with = "::serde_with::rust::double_option")]
     pub batter: Option<Option<PlayerDesc>>,
@@ -61,20 +92,23 @@ pub struct StateDelta {
     pub outs: Option<i64>,
     pub home_score: Option<f64>,
     pub away_score: Option<f64>,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
-#[serde(deny_unknown_fields)]
 pub struct GameUpdateDelta {
     pub changed_state: StateDelta,
     pub display_delay: i64,
     pub display_order: i64,
     pub display_text: String,
     pub display_time: DateTime<Utc>,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct State {
     pub batter: Option<PlayerDesc>,
@@ -126,7 +160,7 @@ impl State {
         if let Some(val) = delta.strikes { self.strikes = val; }
         if let Some(val) = delta.outs { self.outs = val; }
         if let Some(val) = delta.home_score { self.home_score = val; }
-        if let Some(val) = delta.home_score { self.home_score = val; }
+        if let Some(val) = delta.away_score { self.away_score = val; }
     }
 }
 
@@ -141,19 +175,114 @@ pub struct GameUpdate {
     pub display_time: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
-#[serde(deny_unknown_fields)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct GameResponse {
     pub game_id: Uuid,
     pub timestamp: DateTime<Utc>,
     pub data: GameUpdateDelta,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Debug, Clone, Deserialize)]
-#[serde(deny_unknown_fields)]
 pub struct GameEventsResponse {
     pub items: Vec<GameResponse>,
-    pub next_page: String,
+    pub next_page: Option<String>,
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+/// What to do about fields chronicler sends that these types don't know about
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum SchemaMode {
+    /// Keep them in each struct's `extra` and log each field name the first time it turns up
+    #[default]
+    Tolerant,
+    /// Treat them as an error, to catch schema drift
+    Strict,
+}
+
+/// Field names that have already been logged, so each is only logged once
+static LOGGED_UNKNOWN_FIELDS: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(Default::default);
+
+fn check_unknown_fields(fields: Vec<String>, mode: SchemaMode) -> anyhow::Result<()> {
+    if fields.is_empty() {
+        return Ok(());
+    }
+
+    match mode {
+        SchemaMode::Tolerant => {
+            let mut logged = LOGGED_UNKNOWN_FIELDS.lock().unwrap();
+            for field in fields {
+                if !logged.contains(&field) {
+                    warn!(%field, "Chronicler sent a field Fed doesn't know about");
+                    logged.insert(field);
+                }
+            }
+            Ok(())
+        }
+        SchemaMode::Strict => {
+            Err(anyhow!("Chronicler sent fields Fed doesn't know about: {}", fields.join(", ")))
+        }
+    }
+}
+
+impl GameResponse {
+    /// Paths of every field in this item that landed in an `extra` map. A field on several
+    /// defenders or baserunners is only listed once.
+    pub fn unknown_fields(&self) -> Vec<String> {
+        let mut fields = Vec::new();
+        for (field, _) in self.extra_fields() {
+            if !fields.contains(&field) {
+                fields.push(field);
+            }
+        }
+        fields
+    }
+
+    /// Every field in this item that landed in an `extra` map, keyed by the same paths as
+    /// `unknown_fields`. Fields on players and runners are keyed by the state field they're in,
+    /// e.g. `data.changedState.baserunners.speed`.
+    pub fn extra_fields(&self) -> impl Iterator<Item = (String, &serde_json::Value)> {
+        let data = &self.data;
+        let state = &data.changed_state;
+        let players = [("batter", &state.batter), ("pitcher", &state.pitcher)].into_iter()
+            .filter_map(|(name, player)| Some((name, player.as_ref()?.as_ref()?)))
+            .chain(state.defenders.iter().flatten().flatten().map(|player| ("defenders", player)))
+            .flat_map(|(name, player)| player.extra.iter().map(move |(field, value)| (format!("data.changedState.{name}.{field}"), value)));
+        let runners = state.baserunners.iter().flatten()
+            .flat_map(|runner| runner.extra.iter().map(|(field, value)| (format!("data.changedState.baserunners.{field}"), value)));
+        self.extra.iter().map(|(field, value)| (field.clone(), value))
+            .chain(data.extra.iter().map(|(field, value)| (format!("data.{field}"), value)))
+            .chain(state.extra.iter().map(|(field, value)| (format!("data.changedState.{field}"), value)))
+            .chain(players)
+            .chain(runners)
+    }
+
+    pub fn check_unknown_fields(&self, mode: SchemaMode) -> anyhow::Result<()> {
+        check_unknown_fields(self.unknown_fields(), mode)
+    }
+}
+
+impl GameEventsResponse {
+    /// Paths of every field in this page that landed in an `extra` map. Fields in items are
+    /// prefixed with `items.`.
+    pub fn unknown_fields(&self) -> Vec<String> {
+        let mut fields = self.extra.keys().cloned().collect::<Vec<_>>();
+        for item in &self.items {
+            for field in item.unknown_fields() {
+                let field = format!("items.{field}");
+                if !fields.contains(&field) {
+                    fields.push(field);
+                }
+            }
+        }
+        fields
+    }
+
+    pub fn check_unknown_fields(&self, mode: SchemaMode) -> anyhow::Result<()> {
+        check_unknown_fields(self.unknown_fields(), mode)
+    }
 }
 
 impl GameUpdate {
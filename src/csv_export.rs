@@ -0,0 +1,58 @@
+//! Writes the `tables` rows as CSV for spreadsheets. Every file starts with a header row, even if
+//! it has no rows, and the columns are always in the order listed here.
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use serde::Serialize;
+use crate::tables::{BaserunningRow, PitchRow, PlateAppearanceRow, Tables};
+
+pub const PITCH_COLUMNS: &[&str] = &[
+    "game_id", "event_id", "sequence", "inning", "top_of_inning", "batter_id", "batter_name",
+    "pitcher_id", "pitcher_name", "balls", "strikes", "outs", "result", "flavor",
+];
+
+pub const PLATE_APPEARANCE_COLUMNS: &[&str] = &[
+    "game_id", "event_id", "sequence", "inning", "top_of_inning", "batter_id", "batter_name",
+    "pitcher_id", "pitcher_name", "balls", "strikes", "outs", "outcome", "hit_type", "location",
+    "fielder_id", "fielder_name", "flavor", "runs_scored",
+];
+
+pub const BASERUNNING_COLUMNS: &[&str] = &[
+    "game_id", "event_id", "sequence", "inning", "top_of_inning", "runner_id", "runner_name",
+    "from_base", "to_base", "outcome", "event_type",
+];
+
+/// Writes `columns` as the header row, then `rows`, whose fields have to be in the same order
+fn write_table<T: Serialize>(writer: impl Write, columns: &[&str], rows: &[T]) -> anyhow::Result<()> {
+    // csv only writes headers along with the first row, and then only from the field names
+    let mut writer = csv::WriterBuilder::new().has_headers(false).from_writer(writer);
+    writer.write_record(columns)?;
+    for row in rows {
+        writer.serialize(row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn write_pitches(writer: impl Write, rows: &[PitchRow]) -> anyhow::Result<()> {
+    write_table(writer, PITCH_COLUMNS, rows)
+}
+
+pub fn write_plate_appearances(writer: impl Write, rows: &[PlateAppearanceRow]) -> anyhow::Result<()> {
+    write_table(writer, PLATE_APPEARANCE_COLUMNS, rows)
+}
+
+pub fn write_baserunning(writer: impl Write, rows: &[BaserunningRow]) -> anyhow::Result<()> {
+    write_table(writer, BASERUNNING_COLUMNS, rows)
+}
+
+/// Writes `dir/pitches.csv`, `dir/plate_appearances.csv` and `dir/baserunning.csv`, replacing any
+/// that are already there
+pub fn write_tables(dir: &Path, tables: &Tables) -> anyhow::Result<()> {
+    fs::create_dir_all(dir)?;
+    write_pitches(File::create(dir.join("pitches.csv"))?, &tables.pitches)?;
+    write_plate_appearances(File::create(dir.join("plate_appearances.csv"))?, &tables.plate_appearances)?;
+    write_baserunning(File::create(dir.join("baserunning.csv"))?, &tables.baserunning)?;
+    Ok(())
+}
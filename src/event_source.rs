@@ -0,0 +1,257 @@
+use std::fs::File;
+use std::future::Future;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use tracing::debug;
+use crate::chron_schema::{GameEventsResponse, GameResponse, SchemaMode};
+
+/// How many NDJSON lines to return per page, since NDJSON files don't have pages of their own
+const NDJSON_PAGE_SIZE: usize = 1000;
+
+/// Somewhere to get chronicler game events from, one page at a time
+pub trait EventSource {
+    /// Where the source is up to, which can be saved and given back to `resume` later
+    type Cursor: Clone + Serialize + DeserializeOwned + Send + 'static;
+
+    /// Returns the next page of items in the order they were served, or `None` once there are no
+    /// more. An empty page means the source has caught up but may have more items later.
+    fn next_page(&mut self) -> impl Future<Output=anyhow::Result<Option<Vec<GameResponse>>>> + Send;
+
+    /// Where to carry on from to get the pages after the ones already returned
+    fn cursor(&self) -> Self::Cursor;
+
+    /// Carries on from a cursor saved by an earlier run over the same source
+    fn resume(&mut self, cursor: Self::Cursor) -> anyhow::Result<()>;
+}
+
+/// Reads game events saved to disk, either as chronicler game-events pages (`.json`) or as one
+/// game event per line (`.ndjson` or `.jsonl`). Any of these can be compressed with gzip (`.gz`)
+/// or zstd (`.zst`). Directories are read in file name order.
+pub struct FileSource {
+    paths: Vec<PathBuf>,
+    next_path: usize,
+    current_ndjson: Option<NdjsonFile>,
+    schema_mode: SchemaMode,
+}
+
+struct NdjsonFile {
+    path: PathBuf,
+    reader: Box<dyn BufRead + Send>,
+    lines_read: usize,
+}
+
+/// How far through its files a `FileSource` is
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileCursor {
+    /// Index of the first file that hasn't been finished
+    pub file: usize,
+    /// How many lines of that file have already been returned
+    pub line: usize,
+}
+
+impl FileSource {
+    pub fn new(paths: Vec<PathBuf>) -> anyhow::Result<Self> {
+        let mut files = Vec::new();
+        for path in paths {
+            if path.is_dir() {
+                let mut entries = std::fs::read_dir(&path)
+                    .with_context(|| format!("Couldn't read directory {}", path.display()))?
+                    .map(|entry| entry.map(|entry| entry.path()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                entries.retain(|entry| entry.is_file());
+                entries.sort();
+                files.extend(entries);
+            } else {
+                files.push(path);
+            }
+        }
+
+        Ok(Self {
+            paths: files,
+            next_path: 0,
+            current_ndjson: None,
+            schema_mode: SchemaMode::default(),
+        })
+    }
+
+    pub fn schema_mode(mut self, schema_mode: SchemaMode) -> Self {
+        self.schema_mode = schema_mode;
+        self
+    }
+
+    fn next_page_sync(&mut self) -> anyhow::Result<Option<Vec<GameResponse>>> {
+        loop {
+            if let Some(NdjsonFile { path, reader, lines_read }) = &mut self.current_ndjson {
+                let mut items = Vec::new();
+                for line in reader.lines().take(NDJSON_PAGE_SIZE) {
+                    let line = line.with_context(|| format!("Couldn't read {}", path.display()))?;
+                    *lines_read += 1;
+                    if line.trim().is_empty() { continue; }
+                    let item: GameResponse = serde_json::from_str(&line)
+                        .with_context(|| format!("Couldn't parse a line of {}", path.display()))?;
+                    item.check_unknown_fields(self.schema_mode)
+                        .with_context(|| format!("In line {lines_read} of {}", path.display()))?;
+                    items.push(item);
+                }
+
+                if !items.is_empty() {
+                    return Ok(Some(items));
+                }
+                self.current_ndjson = None;
+            }
+
+            let Some(path) = self.paths.get(self.next_path).cloned() else {
+                return Ok(None);
+            };
+            self.next_path += 1;
+            debug!(path = %path.display(), "Reading saved game events");
+            let (reader, format) = open(&path)?;
+            match format {
+                Format::Page => {
+                    let page: GameEventsResponse = serde_json::from_reader(reader)
+                        .with_context(|| format!("Couldn't parse {}", path.display()))?;
+                    page.check_unknown_fields(self.schema_mode)
+                        .with_context(|| format!("In {}", path.display()))?;
+                    return Ok(Some(page.items));
+                }
+                Format::Ndjson => {
+                    self.current_ndjson = Some(NdjsonFile { path, reader, lines_read: 0 });
+                }
+            }
+        }
+    }
+}
+
+impl EventSource for FileSource {
+    type Cursor = FileCursor;
+
+    async fn next_page(&mut self) -> anyhow::Result<Option<Vec<GameResponse>>> {
+        self.next_page_sync()
+    }
+
+    fn cursor(&self) -> FileCursor {
+        match &self.current_ndjson {
+            Some(file) => { FileCursor { file: self.next_path - 1, line: file.lines_read } }
+            None => { FileCursor { file: self.next_path, line: 0 } }
+        }
+    }
+
+    fn resume(&mut self, cursor: FileCursor) -> anyhow::Result<()> {
+        self.next_path = cursor.file;
+        self.current_ndjson = None;
+        if cursor.line > 0 {
+            let path = self.paths.get(cursor.file).cloned()
+                .ok_or_else(|| anyhow!("Can't resume from file {}, there are only {} files", cursor.file, self.paths.len()))?;
+            let (mut reader, _) = open(&path)?;
+            let mut line = String::new();
+            for _ in 0..cursor.line {
+                line.clear();
+                reader.read_line(&mut line)
+                    .with_context(|| format!("Couldn't read {}", path.display()))?;
+            }
+            self.next_path += 1;
+            self.current_ndjson = Some(NdjsonFile { path, reader, lines_read: cursor.line });
+        }
+        Ok(())
+    }
+}
+
+enum Format {
+    Page,
+    Ndjson,
+}
+
+/// Opens `path`, undoing any compression, and works out which format it's in from its extensions
+fn open(path: &Path) -> anyhow::Result<(Box<dyn BufRead + Send>, Format)> {
+    let file = File::open(path)
+        .with_context(|| format!("Couldn't open {}", path.display()))?;
+    let (reader, inner_path): (Box<dyn Read + Send>, _) = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("gz") => { (Box::new(flate2::read::MultiGzDecoder::new(file)), path.with_extension("")) }
+        Some("zst") => { (Box::new(zstd::Decoder::new(file)?), path.with_extension("")) }
+        _ => { (Box::new(file), path.to_path_buf()) }
+    };
+
+    let format = match inner_path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => { Format::Page }
+        Some("ndjson" | "jsonl") => { Format::Ndjson }
+        _ => { return Err(anyhow!("Can't tell what format {} is in from its extension", path.display())); }
+    };
+
+    Ok((Box::new(BufReader::new(reader)), format))
+}
+
+/// Wraps an `EventSource` so that each batch it returns holds every item for each of its
+/// timestamps. Pages can end partway through a timestamp, and grouping a page by timestamp would
+/// then split that timestamp's items across two groups.
+pub struct TimestampBatches<S> {
+    source: S,
+    exhausted: bool,
+    // Items that share a timestamp with the end of the last page, which may continue on the next
+    held_back: Vec<GameResponse>,
+}
+
+impl<S: EventSource> TimestampBatches<S> {
+    pub fn new(source: S) -> Self {
+        Self::resume(source, Vec::new())
+    }
+
+    /// Carries on from a source that's been resumed, along with the items that were held back
+    /// when its cursor was saved
+    pub fn resume(source: S, held_back: Vec<GameResponse>) -> Self {
+        Self {
+            source,
+            exhausted: false,
+            held_back,
+        }
+    }
+
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    /// Items the source has already returned that haven't been handed over yet
+    pub fn held_back(&self) -> &[GameResponse] {
+        &self.held_back
+    }
+
+    /// Returns the next batch of items in the order the source returned them, or `None` once
+    /// there are no more
+    pub async fn next_items(&mut self) -> anyhow::Result<Option<Vec<GameResponse>>> {
+        loop {
+            if self.exhausted {
+                return Ok(if self.held_back.is_empty() {
+                    None
+                } else {
+                    Some(std::mem::take(&mut self.held_back))
+                });
+            }
+
+            let mut items = std::mem::take(&mut self.held_back);
+            match self.source.next_page().await? {
+                Some(page) if page.is_empty() => {
+                    // The source has caught up, so nothing more is coming for the held back
+                    // timestamp yet. Hand it over rather than waiting on the next page.
+                }
+                Some(page) => {
+                    items.extend(page);
+                    if let Some(last_timestamp) = items.last().map(|item| item.timestamp) {
+                        let held_back_start = items.iter()
+                            .rposition(|item| item.timestamp != last_timestamp)
+                            .map_or(0, |i| i + 1);
+                        self.held_back = items.split_off(held_back_start);
+                    }
+                }
+                None => {
+                    self.exhausted = true;
+                }
+            }
+
+            if !items.is_empty() {
+                return Ok(Some(items));
+            }
+        }
+    }
+}
@@ -1,9 +1,54 @@
+//! Fed events, and the JSON they're stored as.
+//!
+//! Every type here implements `Serialize` and `Deserialize`, and the JSON round-trips losslessly.
+//! The representation is part of Fed's interface, so changing it is a breaking change:
+//!
+//! - An `Event` is an object whose `type` field names the variant in snake_case (`play_ball`,
+//!   `batter_up`, `ball`, `fielders_choice`, ...). The variant's fields sit alongside `type`.
+//! - Every other enum is written the serde default way with snake_case variant names. Variants
+//!   without data are plain strings (`"ball_comma"`, `"second"`). Variants with data are an object
+//!   with a single key, the variant name, holding the data: `{"adjective": "auspicious"}`, or an
+//!   array for variants with several unnamed fields, e.g. `RunnerAdvancement::Advanced` is
+//!   `{"advanced": ["second", "advances_to"]}`.
+//! - Struct fields keep their Rust names. Optional fields are `null` when absent.
+//! - Players are `{"id": <uuid>, "name": <name>}`. Runners also have `base`, where 1 is first.
+//! - Events are stored wrapped in a `FedEvent`, whose fields sit alongside an `event` field
+//!   holding the `Event`. Its `schema_version` says which version of this format the event was
+//!   written in. Older versions can be brought up to date with `migrations::upgrade`.
+//!
+//! For example, a ball:
+//!
+//! ```json
+//! {"type": "ball", "flavor": {"adjective": "auspicious"}}
+//! ```
+//!
+//! and a flyout:
+//!
+//! ```json
+//! {
+//!   "type": "flyout",
+//!   "contact": {
+//!     "batter": {"id": "...", "name": "Bat Oman"},
+//!     "location": "deep_left_field",
+//!     "flavor": {"named_with_sound": {"sound_effect": "wham", "verb": "hits"}}
+//!   },
+//!   "defender": {"id": "...", "name": "Glove Person"},
+//!   "flavor": "fly_out_to"
+//! }
+//! ```
+
+use std::collections::BTreeMap;
 use std::fmt::{Debug, Display, Formatter, write};
 use anyhow::anyhow;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ts_rs::{TypeVisitor, TS};
 use uuid::Uuid;
 use crate::chron_schema::{GameUpdate, PlayerDesc, RunnerDesc, State, TeamAtBat};
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "snake_case")]
 pub enum PitchAdjective {
     Auspicious,
     Average,
@@ -40,7 +85,8 @@ impl Display for PitchAdjective {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "snake_case")]
 pub enum BallFlavor {
     DoesntBlink,
     JustMisses,
@@ -60,7 +106,8 @@ pub enum BallFlavor {
     DoesNotChase,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "snake_case")]
 pub enum SwingAdjective {
     Pathetic,
     Poor,
@@ -79,7 +126,8 @@ impl Display for SwingAdjective {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "snake_case")]
 pub enum StrikeFlavor {
     None,
     Looking,
@@ -93,7 +141,8 @@ pub enum StrikeFlavor {
     SwingsWeakly,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "snake_case")]
 pub enum SoundEffect {
     Bam,
     Boom,
@@ -118,7 +167,8 @@ impl Display for SoundEffect {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "snake_case")]
 pub enum ContactVerb {
     Bats,
     Chops,
@@ -169,7 +219,8 @@ impl Display for ContactVerb {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "snake_case")]
 pub enum PitchDescriptor {
     It,
     One,
@@ -188,7 +239,8 @@ impl Display for PitchDescriptor {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "snake_case")]
 pub enum FieldLocation {
     Infield,
     LeftField,
@@ -215,7 +267,8 @@ impl Display for FieldLocation {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "snake_case")]
 pub enum ContactAdjective {
     Decent,
     Depressing,
@@ -240,7 +293,8 @@ impl Display for ContactAdjective {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "snake_case")]
 pub enum ContactFlavor {
     NamedWithSound {
         sound_effect: SoundEffect,
@@ -255,7 +309,7 @@ pub enum ContactFlavor {
     },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, TS)]
 pub struct Contact {
     pub batter: PlayerDesc,
     pub location: Option<FieldLocation>,
@@ -293,7 +347,8 @@ impl Display for Contact {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "snake_case")]
 pub enum FoulFlavor {
     FoulBall,
     FoulTip,
@@ -301,7 +356,8 @@ pub enum FoulFlavor {
     FoulsItOff,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "snake_case")]
 pub enum CatchAdjective {
     Cool,
     Decent,
@@ -334,7 +390,8 @@ impl Display for CatchAdjective {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "snake_case")]
 pub enum FlyoutFlavor {
     FlyOutTo,
     IsRightThere,
@@ -342,14 +399,16 @@ pub enum FlyoutFlavor {
     MakesCatchWithAdjective(CatchAdjective),
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "snake_case")]
 pub enum GroundoutFlavor {
     GroundOutTo,
     HitsAGroundout,
     ForcedOutAtFirst,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "snake_case")]
 pub enum FieldingFlavor {
     ChargesForIt,
     CollectsIt,
@@ -413,7 +472,7 @@ impl Display for FieldingFlavor {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, TS)]
 pub struct Fielding {
     pub defender: PlayerDesc,
     pub flavor: FieldingFlavor,
@@ -425,7 +484,8 @@ impl Display for Fielding {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "snake_case")]
 pub enum FailedFieldingFlavor {
     BobblesIt,
     CantCollectIt,
@@ -460,7 +520,7 @@ impl Display for FailedFieldingFlavor {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, TS)]
 pub struct FailedFielding {
     pub defender: PlayerDesc,
     pub flavor: FailedFieldingFlavor,
@@ -472,13 +532,15 @@ impl Display for FailedFielding {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "snake_case")]
 pub enum StrikeoutFlavor {
     NamedBoth,
     NamedBatter,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "snake_case")]
 pub enum HitType {
     Single,
     Double,
@@ -495,7 +557,19 @@ impl Display for HitType {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+impl HitType {
+    /// The base the batter ends up on after this hit
+    pub fn base(&self) -> Base {
+        match self {
+            HitType::Single => { Base::First }
+            HitType::Double => { Base::Second }
+            HitType::Triple => { Base::Third }
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "snake_case")]
 pub enum HitFlavor {
     Hits,
     IsOnWith,
@@ -510,7 +584,8 @@ impl Display for HitFlavor {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "snake_case")]
 pub enum MaybeFailedFielding {
     Fielding(Fielding),
     FailedFielding(FailedFielding),
@@ -537,7 +612,8 @@ impl Into<MaybeFailedFielding> for FailedFielding {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "snake_case")]
 pub enum Base {
     First,
     Second,
@@ -554,7 +630,19 @@ impl Display for Base {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+impl Base {
+    /// The value chronicler uses for this base in `RunnerDesc::base`, which counts from 1 for first
+    pub fn index(&self) -> i64 {
+        match self {
+            Base::First => { 1 }
+            Base::Second => { 2 }
+            Base::Third => { 3 }
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "snake_case")]
 pub enum AdvancementFlavor {
     To,
     AdvancesTo,
@@ -569,38 +657,49 @@ impl Display for AdvancementFlavor {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, TS)]
 pub struct Advancement {
     pub runner: PlayerDesc,
     pub to_base: Base,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "snake_case")]
 pub enum RunnerAdvancement {
     None,
     Advanced(Base, AdvancementFlavor),
     Scored,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, TS)]
 pub struct RunnerAdvancementDesc {
     pub runner: RunnerDesc,
     pub advancement: RunnerAdvancement,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "snake_case")]
 pub enum WalkFlavor {
     Ball4,
     DrawsWalk,
     EarnsWalk,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum Event {
     PlayBall,
-    BatterUp,
-    Ball(BallFlavor),
-    Strike(StrikeFlavor),
+    BatterUp {
+        batter: PlayerDesc,
+        /// `None` if chronicler didn't have a pitcher when the batter stepped up
+        pitcher: Option<PlayerDesc>,
+    },
+    Ball {
+        flavor: BallFlavor,
+    },
+    Strike {
+        flavor: StrikeFlavor,
+    },
     Flyout {
         contact: Contact,
         defender: PlayerDesc,
@@ -616,9 +715,13 @@ pub enum Event {
         batter: PlayerDesc,
         flavor: StrikeoutFlavor,
     },
-    Foul(FoulFlavor),
+    Foul {
+        flavor: FoulFlavor,
+    },
     HomeRun {
         contact: Contact,
+        /// Runners who were on base, and scored ahead of the batter
+        scores: Vec<PlayerDesc>,
     },
     Hit {
         contact: Contact,
@@ -634,6 +737,7 @@ pub enum Event {
     },
     EndOfHalfInning {
         top_of_inning: bool,
+        #[ts(type = "number")]
         inning: i64,
     },
     FieldersChoice {
@@ -643,6 +747,80 @@ pub enum Event {
     }
 }
 
+/// The version of the Fed JSON format this build reads and writes. Bump it, and add a step to
+/// `migrations`, whenever a change to these types changes the JSON.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// An `Event` along with which game it's from and which chronicler updates it was parsed from.
+/// This is the unit Fed stores and hands on.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, TS)]
+pub struct FedEvent {
+    /// Always `SCHEMA_VERSION` for events written by this build
+    pub schema_version: u32,
+    /// Derived from the game id and `first_display_order`, so parsing the same game again gives
+    /// the same ids
+    pub id: Uuid,
+    pub game_id: Uuid,
+    /// The event's position in its game, starting from 0
+    #[ts(type = "number")]
+    pub sequence: u64,
+    /// Chronicler timestamp of the event's first update
+    pub timestamp: DateTime<Utc>,
+    /// Display time of the event's first update
+    pub display_time: DateTime<Utc>,
+    /// Display order of the event's first update
+    #[ts(type = "number")]
+    pub first_display_order: i64,
+    /// Display order of the event's last update
+    #[ts(type = "number")]
+    pub last_display_order: i64,
+    pub event: Event,
+}
+
+impl FedEvent {
+    pub fn id_for(game_id: Uuid, first_display_order: i64) -> Uuid {
+        Uuid::new_v5(&game_id, first_display_order.to_string().as_bytes())
+    }
+}
+
+/// A JSON Schema for a stored `FedEvent`, with every `Event` variant and nested type under
+/// `$defs`. Generated from these types, so it always matches what this build writes.
+pub fn json_schema() -> schemars::Schema {
+    schemars::schema_for!(FedEvent)
+}
+
+/// TypeScript declarations for `FedEvent` and every type it contains, as one module sorted by
+/// type name. The checked-in copy is `bindings/fed.d.ts`.
+pub fn typescript_definitions() -> String {
+    #[derive(Default)]
+    struct Declarations(BTreeMap<String, String>);
+
+    impl TypeVisitor for Declarations {
+        fn visit<T: TS + 'static + ?Sized>(&mut self) {
+            // Types without an output path are built in, like `string` for `Uuid`
+            if T::output_path().is_none() || self.0.contains_key(&T::name()) {
+                return;
+            }
+            let mut declaration = T::docs().unwrap_or_default();
+            declaration.push_str("export ");
+            declaration.push_str(&T::decl());
+            self.0.insert(T::name(), declaration);
+            T::visit_dependencies(self);
+        }
+    }
+
+    let mut declarations = Declarations::default();
+    declarations.visit::<FedEvent>();
+
+    let mut out = "// Generated from fed_schema.rs by `fed2 typescript`. Don't edit by hand.\n".to_string();
+    for declaration in declarations.0.values() {
+        out.push('\n');
+        out.push_str(declaration);
+        out.push('\n');
+    }
+    out
+}
+
 struct Count(i64, i64);
 
 impl Display for Count {
@@ -657,12 +835,10 @@ impl Event {
             Event::PlayBall => {
                 vec!["Play Ball!".to_string()]
             }
-            Event::BatterUp => {
-                let batter = state.batter.as_ref()
-                    .ok_or_else(|| anyhow!("Expected non-null batter in BatterUp event"))?;
+            Event::BatterUp { batter, .. } => {
                 vec![format!("{} steps up to bat.", batter)]
             }
-            Event::Ball(flavor) => {
+            Event::Ball { flavor } => {
                 let count = Count(state.balls, state.strikes);
                 let pitcher = state.pitcher.as_ref()
                     .ok_or_else(|| anyhow!("Expected non-null pitcher in a Ball event"))?;
@@ -687,7 +863,7 @@ impl Event {
                 };
                 vec![text, String::new()]
             }
-            Event::Strike(flavor) => {
+            Event::Strike { flavor } => {
                 let count = Count(state.balls, state.strikes);
                 let pitcher = state.pitcher.as_ref()
                     .ok_or_else(|| anyhow!("Expected non-null pitcher in a Strike event"))?;
@@ -726,7 +902,7 @@ impl Event {
                 };
                 vec![text]
             }
-            Event::Foul(flavor) => {
+            Event::Foul { flavor } => {
                 let count = Count(state.balls, state.strikes);
                 let batter = state.batter.as_ref()
                     .ok_or_else(|| anyhow!("Expected non-null batter in a Foul event"))?;
@@ -765,7 +941,7 @@ impl Event {
 
                 result
             }
-            Event::HomeRun { contact } => {
+            Event::HomeRun { contact, .. } => {
                 vec![
                     contact.to_string(),
                     format!("{} hits a Home Run!", contact.batter),
@@ -0,0 +1,382 @@
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use reqwest::{Request, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+use tracing::{debug, warn};
+use uuid::Uuid;
+use crate::chron_schema::{GameEventsResponse, GameResponse, SchemaMode};
+use crate::chronicler::{request_interval, ChroniclerClient, GameEventsQuery};
+use crate::event_source::EventSource;
+
+/// Where the fetcher gets its responses from
+#[derive(Debug, Clone, Default)]
+pub enum FixtureMode {
+    /// Fetch from chronicler
+    #[default]
+    Live,
+    /// Fetch from chronicler and save every response body to this directory, so the run can be
+    /// reproduced byte-for-byte later
+    Record(PathBuf),
+    /// Serve the responses saved by `Record` to this directory instead of using the network
+    Replay(PathBuf),
+}
+
+/// Recorded responses are keyed by request URL
+fn fixture_path(dir: &Path, url: &Url) -> PathBuf {
+    dir.join(format!("{}.json", Uuid::new_v5(&Uuid::NAMESPACE_URL, url.as_str().as_bytes())))
+}
+
+/// How hard to try before giving up on a page
+#[derive(Debug, Copy, Clone)]
+pub struct RetryPolicy {
+    /// Attempts per page, including the first
+    pub max_attempts: u32,
+    /// How long to wait after the first failure. Doubles after each failure after that.
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// How long to wait after failing `attempt` times. Jittered so that many clients failing at
+    /// once don't all come back at once.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let backoff = self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_backoff);
+        backoff.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+    }
+}
+
+/// Spaces requests out so there are at most a fixed number per second
+#[derive(Debug)]
+struct RateLimiter {
+    interval: Duration,
+    next_request: Option<Instant>,
+}
+
+impl RateLimiter {
+    async fn wait(&mut self) {
+        if let Some(next_request) = self.next_request {
+            tokio::time::sleep_until(next_request).await;
+        }
+        self.next_request = Some(Instant::now() + self.interval);
+    }
+}
+
+/// Why a page of game events couldn't be fetched. Carries where the failed page started, so the
+/// run can carry on from there: pass the page token to `starting_at_page`, or if there wasn't one
+/// (when tailing past chronicler's last page), the time as `GameEventsQuery::after`.
+#[derive(Debug, thiserror::Error)]
+pub enum FetchError {
+    #[error("Gave up fetching {} after {attempts} attempts", PageName(.page, .after))]
+    RetriesExhausted {
+        page: Option<String>,
+        after: Option<DateTime<Utc>>,
+        attempts: u32,
+        #[source]
+        source: reqwest::Error,
+    },
+    #[error("Couldn't fetch {}", PageName(.page, .after))]
+    Permanent {
+        page: Option<String>,
+        after: Option<DateTime<Utc>>,
+        #[source]
+        source: anyhow::Error,
+    },
+}
+
+impl FetchError {
+    /// The token of the page that failed, or `None` if it was the first page or didn't have one
+    pub fn page(&self) -> Option<&str> {
+        match self {
+            FetchError::RetriesExhausted { page, .. } => { page.as_deref() }
+            FetchError::Permanent { page, .. } => { page.as_deref() }
+        }
+    }
+
+    /// The time the failed page asked for events after, if it had no page token because it was
+    /// polling for new events
+    pub fn after(&self) -> Option<DateTime<Utc>> {
+        match self {
+            FetchError::RetriesExhausted { after, .. } => { *after }
+            FetchError::Permanent { after, .. } => { *after }
+        }
+    }
+}
+
+struct PageName<'a>(&'a Option<String>, &'a Option<DateTime<Utc>>);
+
+impl Display for PageName<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PageName(Some(page), _) => { write!(f, "game events page {page}") }
+            PageName(None, Some(after)) => { write!(f, "the game events newer than {}", after.to_rfc3339()) }
+            PageName(None, None) => { write!(f, "the first page of game events") }
+        }
+    }
+}
+
+/// Whether trying the same request again might work
+fn is_transient(err: &reqwest::Error) -> bool {
+    err.is_timeout() || err.is_connect() || err.is_body() ||
+        err.status().is_some_and(|status| status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS)
+}
+
+/// Where a `GameEventsFetcher` is up to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FetcherCursor {
+    pub next_page: Option<String>,
+    pub last_timestamp: Option<DateTime<Utc>>,
+    pub pages_fetched: usize,
+    pub exhausted: bool,
+}
+
+/// Fetches game events page by page, following `next_page` until chronicler runs out of items or
+/// the page limit is reached. When tailing, running out of items just means waiting for more.
+pub struct GameEventsFetcher {
+    client: ChroniclerClient,
+    query: GameEventsQuery,
+    max_pages: Option<usize>,
+    pages_fetched: usize,
+    next_page: Option<String>,
+    // Only used as the cursor when tailing and chronicler didn't give a page token
+    last_timestamp: Option<DateTime<Utc>>,
+    exhausted: bool,
+    fixtures: FixtureMode,
+    poll_interval: Option<Duration>,
+    retry: RetryPolicy,
+    rate_limiter: Option<RateLimiter>,
+    schema_mode: SchemaMode,
+}
+
+impl GameEventsFetcher {
+    pub fn new(client: ChroniclerClient, query: GameEventsQuery, max_pages: Option<usize>, fixtures: FixtureMode) -> Self {
+        Self {
+            client,
+            query,
+            max_pages,
+            pages_fetched: 0,
+            next_page: None,
+            last_timestamp: None,
+            exhausted: false,
+            fixtures,
+            poll_interval: None,
+            retry: RetryPolicy::default(),
+            rate_limiter: None,
+            schema_mode: SchemaMode::default(),
+        }
+    }
+
+    pub fn schema_mode(mut self, schema_mode: SchemaMode) -> Self {
+        self.schema_mode = schema_mode;
+        self
+    }
+
+    /// Start from this page token instead of the first page, e.g. to carry on after a `FetchError`
+    pub fn starting_at_page(mut self, page: String) -> Self {
+        self.next_page = Some(page);
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Make at most `requests_per_second` requests per second, which has to be finite and more
+    /// than 0
+    pub fn rate_limit(mut self, requests_per_second: f64) -> anyhow::Result<Self> {
+        self.rate_limiter = Some(RateLimiter {
+            interval: request_interval(requests_per_second)?,
+            next_request: None,
+        });
+        Ok(self)
+    }
+
+    /// Keep polling for new items every `poll_interval` instead of stopping once chronicler runs
+    /// out. While caught up, `next_page` returns empty pages once per poll.
+    pub fn tail(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = Some(poll_interval);
+        self
+    }
+
+    /// The time the next page asks for events after, when it's polling after the last item seen
+    /// instead of following a page token
+    fn polling_after(&self) -> Option<DateTime<Utc>> {
+        match self.next_page {
+            None => { self.last_timestamp }
+            Some(_) => { None }
+        }
+    }
+
+    fn permanent_error(&self, source: anyhow::Error) -> FetchError {
+        FetchError::Permanent { page: self.next_page.clone(), after: self.polling_after(), source }
+    }
+
+    async fn fetch_page(&mut self) -> Result<GameEventsResponse, FetchError> {
+        let request = match self.polling_after() {
+            Some(after) => {
+                let query = GameEventsQuery { after: Some(after), ..self.query.clone() };
+                self.client.game_events(&query, None)
+            }
+            None => {
+                self.client.game_events(&self.query, self.next_page.as_deref())
+            }
+        };
+        let request = request
+            .and_then(|request| Ok(request.build()?))
+            .map_err(|err| self.permanent_error(err))?;
+
+        let url = request.url().clone();
+        debug!(%url, pages_fetched = self.pages_fetched, "Fetching game events page");
+        let body = match &self.fixtures {
+            FixtureMode::Replay(dir) => {
+                let path = fixture_path(dir, &url);
+                std::fs::read(&path)
+                    .with_context(|| format!("No recorded response for {url} (looked for {})", path.display()))
+                    .map_err(|err| self.permanent_error(err))?
+            }
+            FixtureMode::Live => {
+                self.fetch_with_retries(request).await?
+            }
+            FixtureMode::Record(dir) => {
+                let dir = dir.clone();
+                let body = self.fetch_with_retries(request).await?;
+                record(&dir, &url, &body)
+                    .map_err(|err| self.permanent_error(err))?;
+                body
+            }
+        };
+
+        let page: GameEventsResponse = serde_json::from_slice(&body)
+            .with_context(|| format!("Couldn't parse the response from {url}"))
+            .map_err(|err| self.permanent_error(err))?;
+        page.check_unknown_fields(self.schema_mode)
+            .with_context(|| format!("In the response from {url}"))
+            .map_err(|err| self.permanent_error(err))?;
+        Ok(page)
+    }
+
+    async fn fetch_with_retries(&mut self, request: Request) -> Result<Vec<u8>, FetchError> {
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            if let Some(rate_limiter) = &mut self.rate_limiter {
+                rate_limiter.wait().await;
+            }
+            let attempt = request.try_clone()
+                .ok_or_else(|| self.permanent_error(anyhow!("Couldn't clone the request to retry it")))?;
+            match self.send(attempt).await {
+                Ok(body) => {
+                    return Ok(body);
+                }
+                Err(err) if is_transient(&err) && attempts < self.retry.max_attempts => {
+                    let backoff = self.retry.backoff(attempts);
+                    warn!(%err, attempts, ?backoff, "Fetching game events page failed, retrying");
+                    tokio::time::sleep(backoff).await;
+                }
+                Err(err) if is_transient(&err) => {
+                    return Err(FetchError::RetriesExhausted {
+                        page: self.next_page.clone(),
+                        after: self.polling_after(),
+                        attempts,
+                        source: err,
+                    });
+                }
+                Err(err) => {
+                    return Err(self.permanent_error(err.into()));
+                }
+            }
+        }
+    }
+
+    async fn send(&self, request: Request) -> reqwest::Result<Vec<u8>> {
+        Ok(self.client.http().execute(request).await?
+            .error_for_status()?
+            .bytes().await?
+            .to_vec())
+    }
+}
+
+/// Saves a response body so `FixtureMode::Replay` can serve it later
+fn record(dir: &Path, url: &Url, body: &[u8]) -> anyhow::Result<()> {
+    let path = fixture_path(dir, url);
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Couldn't create fixture directory {}", dir.display()))?;
+    std::fs::write(&path, body)
+        .with_context(|| format!("Couldn't record response to {}", path.display()))?;
+    // Not read back, but it makes the directory possible to navigate
+    let url_path = path.with_extension("url");
+    std::fs::write(&url_path, url.as_str())
+        .with_context(|| format!("Couldn't record the URL of a response to {}", url_path.display()))?;
+    Ok(())
+}
+
+impl EventSource for GameEventsFetcher {
+    type Cursor = FetcherCursor;
+
+    async fn next_page(&mut self) -> anyhow::Result<Option<Vec<GameResponse>>> {
+        if self.exhausted {
+            return Ok(None);
+        }
+
+        let page = self.fetch_page().await?;
+        self.pages_fetched += 1;
+        let reached_limit = self.max_pages.is_some_and(|max_pages| self.pages_fetched >= max_pages);
+        let next_page = page.next_page.filter(|next_page| !next_page.is_empty());
+        let caught_up = page.items.is_empty() || next_page.is_none();
+        if let Some(last) = page.items.last() {
+            self.last_timestamp = Some(last.timestamp);
+        }
+
+        match self.poll_interval {
+            Some(poll_interval) if !reached_limit => {
+                // Without a new page token, the next poll asks for everything after the last
+                // item seen instead. Asking for the same page again would return the same items.
+                self.next_page = next_page;
+                if caught_up {
+                    debug!(?poll_interval, "Caught up, waiting for new game events");
+                    tokio::time::sleep(poll_interval).await;
+                }
+            }
+            _ => {
+                self.next_page = next_page;
+                self.exhausted = caught_up || reached_limit;
+            }
+        }
+
+        Ok(Some(page.items))
+    }
+
+    fn cursor(&self) -> FetcherCursor {
+        FetcherCursor {
+            next_page: self.next_page.clone(),
+            last_timestamp: self.last_timestamp,
+            pages_fetched: self.pages_fetched,
+            exhausted: self.exhausted,
+        }
+    }
+
+    fn resume(&mut self, cursor: FetcherCursor) -> anyhow::Result<()> {
+        self.next_page = cursor.next_page;
+        self.last_timestamp = cursor.last_timestamp;
+        self.pages_fetched = cursor.pages_fetched;
+        self.exhausted = cursor.exhausted;
+        Ok(())
+    }
+}
@@ -0,0 +1,18 @@
+pub mod chron_schema;
+pub mod chronicler;
+pub mod csv_export;
+pub mod event_source;
+pub mod fed_schema;
+pub mod fetcher;
+pub mod migrations;
+pub mod parquet_export;
+pub mod parser;
+pub mod pipeline;
+pub mod proto;
+pub mod reducer;
+pub mod reorder;
+pub mod store;
+pub mod synthesizer;
+pub mod tables;
+pub mod validator;
+mod text_parsers;
@@ -1,59 +1,362 @@
-mod parser;
-mod chron_schema;
-mod text_parsers;
-mod fed_schema;
-
-use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+use clap::{Parser as ClapParser, Subcommand};
 use chrono::{DateTime, Utc};
-use chrono::format::parse;
-use itertools::Itertools;
-use serde::Deserialize;
+use tracing_subscriber::EnvFilter;
 use uuid::Uuid;
 
-use crate::chron_schema::*;
-use crate::parser::Parser;
-
-
-async fn async_main() -> anyhow::Result<()> {
-    println!("Hello, world!");
-    let response = reqwest::get("https://api2.sibr.dev/chronicler/v0/game-events?count=1000").await?;
-    let json: GameEventsResponse = response.json().await?;
-
-    let groups = json.items.into_iter()
-        .group_by(|item| item.timestamp);
-
-    let mut game_parsers: HashMap<_, Parser> = HashMap::new();
-    let mut pending_lines_for_game: HashMap<_, Vec<_>> = HashMap::new();
-    for (_, group) in &groups {
-        let mut group = group.collect_vec();
-        group.sort_by_key(|item| item.data.display_order);
-        for game in group {
-            println!("For game {} at {}", game.game_id, game.timestamp);
-            println!("    Input: {:?}", game.data);
-
-            let mut parser = game_parsers.entry(game.game_id).or_default();
-            let (parsed, state) = parser.parse(game.data.clone())?;
-
-            let mut pending_lines = pending_lines_for_game.entry(game.game_id).or_default();
-            pending_lines.push(game.data.display_text);
-            if let Some(parsed) = parsed {
-                println!("    Output: {parsed:?}");
-                let reconstructed_description = parsed.lines(state)?;
-                assert_eq!(pending_lines, &reconstructed_description);
-                pending_lines.clear();
+use fed2::chron_schema::SchemaMode;
+use fed2::chronicler::{check_requests_per_second, ChroniclerClient, ChroniclerConfig, Order};
+use fed2::event_source::FileSource;
+use fed2::{csv_export, fed_schema, parquet_export, tables};
+use fed2::fetcher::{FixtureMode, GameEventsFetcher, RetryPolicy};
+use fed2::pipeline::{self, CheckpointOptions, GameOutput, OutputSink, PipelineOptions};
+use fed2::store::Store;
+
+/// Parses Blaseball game events from chronicler into Fed
+#[derive(ClapParser)]
+#[command(args_conflicts_with_subcommands = true)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Write parsed events to this file instead of stdout, one JSON object per line. With
+    /// --resume, anything written after the checkpoint is thrown away and the run carries on from
+    /// there.
+    #[arg(long, value_name = "PATH")]
+    output: Option<PathBuf>,
+
+    /// Read saved game events from these files or directories instead of fetching them from
+    /// chronicler. Takes chronicler pages (.json) or one game event per line (.ndjson, .jsonl),
+    /// optionally compressed (.gz, .zst).
+    #[arg(long = "input", value_name = "PATH")]
+    inputs: Vec<PathBuf>,
+
+    /// Save every chronicler response to this directory, keyed by request URL
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["inputs", "replay"])]
+    record: Option<PathBuf>,
+
+    /// Serve chronicler responses from a directory made with --record instead of the network
+    #[arg(long, value_name = "DIR", conflicts_with = "inputs")]
+    replay: Option<PathBuf>,
+
+    /// Keep polling chronicler for new game events instead of stopping at the end, and output
+    /// each event as soon as it's parsed
+    #[arg(long, conflicts_with = "inputs")]
+    tail: bool,
+
+    /// How often to poll chronicler for new game events when tailing
+    #[arg(long, value_name = "SECONDS", default_value_t = 5)]
+    poll_interval: u64,
+
+    /// How far (in chronicler time) a game's updates can arrive out of order before a missing
+    /// update is given up on
+    #[arg(long, value_name = "SECONDS", default_value_t = 60)]
+    reorder_window: i64,
+
+    /// Fail on any field chronicler sends that Fed doesn't know about, instead of logging it and
+    /// carrying on
+    #[arg(long)]
+    strict_schema: bool,
+
+    /// Save progress to this file every so often, so a run that dies can be carried on with
+    /// --resume
+    #[arg(long, value_name = "PATH")]
+    checkpoint: Option<PathBuf>,
+
+    /// How often to save progress to the --checkpoint file
+    #[arg(long, value_name = "SECONDS", default_value_t = 60)]
+    checkpoint_interval: u64,
+
+    /// Carry on from the --checkpoint file instead of starting over. Give the same inputs or
+    /// chronicler settings as the run that saved it.
+    #[arg(long, requires = "checkpoint", conflicts_with = "page")]
+    resume: bool,
+
+    /// Read chronicler settings from this TOML file. Any of the flags below override it.
+    #[arg(long, value_name = "PATH", conflicts_with = "inputs")]
+    config: Option<PathBuf>,
+
+    /// Where the chronicler v0 API lives
+    #[arg(long, value_name = "URL", conflicts_with = "inputs")]
+    base_url: Option<String>,
+
+    /// Only fetch events for this game (repeatable)
+    #[arg(long = "game", value_name = "UUID", conflicts_with = "inputs")]
+    games: Vec<Uuid>,
+
+    /// Only fetch events after this time (RFC 3339)
+    #[arg(long, value_name = "TIME", conflicts_with = "inputs")]
+    after: Option<DateTime<Utc>>,
+
+    /// Only fetch events before this time (RFC 3339)
+    #[arg(long, value_name = "TIME", conflicts_with = "inputs")]
+    before: Option<DateTime<Utc>>,
+
+    /// Which order chronicler returns events in. Only asc, the default, can be ingested.
+    #[arg(long, value_enum, conflicts_with = "inputs")]
+    order: Option<Order>,
+
+    /// How many events to fetch per page
+    #[arg(long, value_name = "COUNT", conflicts_with = "inputs")]
+    page_size: Option<usize>,
+
+    /// Stop after fetching this many pages, even if chronicler has more
+    #[arg(long, value_name = "COUNT", conflicts_with = "inputs")]
+    max_pages: Option<usize>,
+
+    /// How many times to try each page before giving up. Server errors and timeouts are retried
+    /// with exponential backoff.
+    #[arg(long, value_name = "COUNT", conflicts_with = "inputs")]
+    max_attempts: Option<u32>,
+
+    /// Make at most this many requests to chronicler per second
+    #[arg(long, value_name = "RATE", conflicts_with = "inputs", value_parser = parse_requests_per_second)]
+    requests_per_second: Option<f64>,
+
+    /// Start from this page token instead of the first page, e.g. the one reported by a run that
+    /// failed to fetch it. A run that failed while polling with --tail reports a time instead,
+    /// which goes in --after.
+    #[arg(long, value_name = "TOKEN", conflicts_with = "inputs")]
+    page: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the JSON Schema for the Fed events this build writes
+    Schema,
+    /// Print TypeScript declarations for the Fed events this build writes
+    Typescript,
+    /// Flatten Fed NDJSON files (as written by --output) into pitch, plate appearance and
+    /// baserunning tables, as one Parquet file per game and table
+    Parquet {
+        /// Directory to write the tables to
+        #[arg(long, value_name = "DIR")]
+        out: PathBuf,
+        /// Fed NDJSON files to read. Each game's events need to be complete from the start.
+        #[arg(required = true, value_name = "PATH")]
+        inputs: Vec<PathBuf>,
+    },
+    /// Flatten Fed NDJSON files (as written by --output) into pitch, plate appearance and
+    /// baserunning CSV files covering every game
+    Csv {
+        /// Directory to write the tables to
+        #[arg(long, value_name = "DIR")]
+        out: PathBuf,
+        /// Fed NDJSON files to read. Each game's events need to be complete from the start.
+        #[arg(required = true, value_name = "PATH")]
+        inputs: Vec<PathBuf>,
+    },
+    /// Store Fed NDJSON files (as written by --output) in a SQLite database, creating it if need
+    /// be. Games that are already stored are replaced, so files can be ingested again safely.
+    Sqlite {
+        /// The database file
+        #[arg(long, value_name = "PATH")]
+        db: PathBuf,
+        /// Fed NDJSON files to read. Each game's events need to be complete.
+        #[arg(required = true, value_name = "PATH")]
+        inputs: Vec<PathBuf>,
+    },
+}
+
+fn parse_requests_per_second(arg: &str) -> anyhow::Result<f64> {
+    check_requests_per_second(arg.parse()?)
+}
+
+impl Args {
+    /// The config file, if any, with the command line flags applied on top
+    fn chronicler_config(&self) -> anyhow::Result<ChroniclerConfig> {
+        let mut config = match &self.config {
+            Some(path) => { ChroniclerConfig::load(path)? }
+            None => { ChroniclerConfig::default() }
+        };
+        if let Some(base_url) = &self.base_url {
+            config.base_url = base_url.clone();
+        }
+        if !self.games.is_empty() {
+            config.query.games = self.games.clone();
+        }
+        if self.after.is_some() {
+            config.query.after = self.after;
+        }
+        if self.before.is_some() {
+            config.query.before = self.before;
+        }
+        if let Some(order) = self.order {
+            config.query.order = order;
+        }
+        if let Some(page_size) = self.page_size {
+            config.query.count = page_size;
+        }
+        if self.max_pages.is_some() {
+            config.max_pages = self.max_pages;
+        }
+        if self.max_attempts.is_some() {
+            config.max_attempts = self.max_attempts;
+        }
+        if self.requests_per_second.is_some() {
+            config.requests_per_second = self.requests_per_second;
+        }
+
+        Ok(config)
+    }
+}
+
+async fn async_main(args: Args) -> anyhow::Result<()> {
+    let schema_mode = if args.strict_schema { SchemaMode::Strict } else { SchemaMode::Tolerant };
+    let options = PipelineOptions {
+        reorder_window: chrono::Duration::seconds(args.reorder_window),
+        checkpoint: args.checkpoint.clone().map(|path| CheckpointOptions {
+            path,
+            interval: Duration::from_secs(args.checkpoint_interval),
+            resume: args.resume,
+        }),
+    };
+    let write = match &args.output {
+        Some(path) => {
+            // A resumed run keeps what the run it's resuming wrote, up to its checkpoint
+            let mut file = OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(!args.resume)
+                .open(path)?;
+            let position = file.seek(SeekFrom::End(0))?;
+            EventWriter::File { file: BufWriter::new(file), position }
+        }
+        None => { EventWriter::Stdout(io::stdout()) }
+    };
+    if args.inputs.is_empty() {
+        let config = args.chronicler_config()?;
+        if config.query.order == Order::Desc {
+            return Err(anyhow::anyhow!("Can't ingest with order desc, since each game's events have to be parsed oldest first. Use order asc, the default."));
+        }
+        let client = ChroniclerClient::new(&config.base_url)?;
+        let fixtures = match (args.record, args.replay) {
+            (Some(dir), _) => FixtureMode::Record(dir),
+            (_, Some(dir)) => FixtureMode::Replay(dir),
+            (None, None) => FixtureMode::Live,
+        };
+        let mut fetcher = GameEventsFetcher::new(client, config.query, config.max_pages, fixtures)
+            .schema_mode(schema_mode);
+        if let Some(max_attempts) = config.max_attempts {
+            fetcher = fetcher.retry(RetryPolicy { max_attempts, ..RetryPolicy::default() });
+        }
+        if let Some(requests_per_second) = config.requests_per_second {
+            fetcher = fetcher.rate_limit(requests_per_second)?;
+        }
+        if let Some(page) = args.page {
+            fetcher = fetcher.starting_at_page(page);
+        }
+        if args.tail {
+            fetcher = fetcher.tail(Duration::from_secs(args.poll_interval));
+        }
+        pipeline::run(fetcher, options, write).await
+    } else {
+        pipeline::run(FileSource::new(args.inputs)?.schema_mode(schema_mode), options, write).await
+    }
+}
+
+/// Where parsed events go. Only a file can be wound back to a checkpoint.
+enum EventWriter {
+    Stdout(io::Stdout),
+    File { file: BufWriter<File>, position: u64 },
+}
+
+impl OutputSink for EventWriter {
+    /// Writes the output's event, if it has one, as a line of JSON along with any fields chronicler
+    /// sent for it that Fed doesn't know about. Each line is flushed straight away so whatever's
+    /// reading it sees events as they're parsed.
+    fn write(&mut self, output: GameOutput) -> anyhow::Result<()> {
+        let Some(event) = output.event else {
+            return Ok(());
+        };
+        let mut line = serde_json::to_vec(&event)?;
+        line.push(b'\n');
+        match self {
+            EventWriter::Stdout(out) => {
+                out.write_all(&line)?;
+                out.flush()?;
+            }
+            EventWriter::File { file, position } => {
+                file.write_all(&line)?;
+                file.flush()?;
+                *position += line.len() as u64;
             }
+        }
+        Ok(())
+    }
+
+    fn position(&mut self) -> anyhow::Result<Option<u64>> {
+        Ok(match self {
+            EventWriter::Stdout(_) => { None }
+            EventWriter::File { position, .. } => { Some(*position) }
+        })
+    }
 
+    fn rewind(&mut self, to: u64) -> anyhow::Result<()> {
+        if let EventWriter::File { file, position } = self {
+            if to > *position {
+                return Err(anyhow::anyhow!("The output is shorter than when the checkpoint was saved, so it isn't the same file"));
+            }
+            file.flush()?;
+            file.get_ref().set_len(to)?;
+            file.seek(SeekFrom::Start(to))?;
+            *position = to;
         }
+        Ok(())
     }
-    Ok(())
 }
 
 fn main() -> anyhow::Result<()> {
-    tokio::runtime::Builder::new_current_thread()
+    let args = Args::parse();
+
+    // Logs go to stderr so they don't interleave with parser or subcommand output. Use RUST_LOG to
+    // pick what to see, e.g. `RUST_LOG=fed2::parser=trace` to follow every parser state transition.
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_writer(std::io::stderr)
+        .init();
+
+    match args.command {
+        Some(Command::Schema) => {
+            println!("{}", serde_json::to_string_pretty(&fed_schema::json_schema())?);
+            return Ok(());
+        }
+        Some(Command::Typescript) => {
+            print!("{}", fed_schema::typescript_definitions());
+            return Ok(());
+        }
+        Some(Command::Parquet { out, inputs }) => {
+            for events in tables::load_games(&inputs)? {
+                parquet_export::write_game(&out, events[0].game_id, &tables::flatten(&events)?)?;
+            }
+            return Ok(());
+        }
+        Some(Command::Csv { out, inputs }) => {
+            let mut all = tables::Tables::default();
+            for events in tables::load_games(&inputs)? {
+                all.append(tables::flatten(&events)?);
+            }
+            csv_export::write_tables(&out, &all)?;
+            return Ok(());
+        }
+        Some(Command::Sqlite { db, inputs }) => {
+            let mut store = Store::open(db)?;
+            for events in tables::load_games(&inputs)? {
+                store.ingest(&events)?;
+            }
+            return Ok(());
+        }
+        None => {}
+    }
+
+    tokio::runtime::Builder::new_multi_thread()
         .enable_all()
         .build()
         .unwrap()
         .block_on(async {
-            async_main().await
+            async_main(args).await
         })
 }
\ No newline at end of file
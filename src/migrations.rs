@@ -0,0 +1,113 @@
+//! Upgrades Fed JSON written by older builds to the current `SCHEMA_VERSION`.
+//!
+//! Each step takes events from one version to the next, working on the raw JSON so that it
+//! doesn't depend on what the types look like now. Events written before `schema_version` existed
+//! are version 1.
+//!
+//! The exception is `v1_to_v2`, which has to replay each game to find who was on base, and does it
+//! with the current `Event` type and `reducer::apply`. It relies on every event type's version 2
+//! JSON still deserializing as the current `Event`, and on the reducer tracking baserunners the
+//! same way it did in version 2. Changing either means first giving that step its own copy of the
+//! version 2 types and replay. The saved version 1 game in the migration tests catches it if not.
+
+use std::collections::BTreeMap;
+use serde_json::{json, Value};
+use crate::chron_schema::State;
+use crate::fed_schema::{Event, FedEvent, SCHEMA_VERSION};
+use crate::reducer::apply;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MigrationError {
+    #[error("Event is schema version {found}, but this build only reads up to version {SCHEMA_VERSION}")]
+    UnsupportedVersion { found: u32 },
+    #[error("Event is missing its {0} field")]
+    MissingField(&'static str),
+    #[error("Game {game_id} needs its events from the start, in order, to upgrade from version {from}, but sequence {expected} is missing")]
+    IncompleteGame { game_id: String, from: u32, expected: u64 },
+    #[error("Malformed event: {0}")]
+    Malformed(#[from] serde_json::Error),
+}
+
+/// Takes a game's events from `from` to `from + 1`. Only touches events at version `from`.
+type Step = fn(game_id: &str, events: &mut [Value]) -> Result<(), MigrationError>;
+
+/// `STEPS[i]` upgrades from version `i + 1`
+const STEPS: [Step; (SCHEMA_VERSION - 1) as usize] = [
+    v1_to_v2,
+];
+
+fn version(event: &Value) -> u32 {
+    event.get("schema_version")
+        .and_then(Value::as_u64)
+        .map_or(1, |version| version as u32)
+}
+
+fn field<'a>(event: &'a Value, name: &'static str) -> Result<&'a Value, MigrationError> {
+    event.get(name).ok_or(MigrationError::MissingField(name))
+}
+
+/// Upgrades serialized `FedEvent`s of any version this build knows about to the current version.
+///
+/// Some steps need to know what happened earlier in the game, so events for a game are upgraded
+/// together, and any game with old enough events needs all of its events from the start. Events
+/// come back grouped by game, in sequence order.
+pub fn upgrade(events: Vec<Value>) -> Result<Vec<FedEvent>, MigrationError> {
+    let mut games: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+    for event in events {
+        let found = version(&event);
+        if found > SCHEMA_VERSION {
+            return Err(MigrationError::UnsupportedVersion { found });
+        }
+        let game_id = field(&event, "game_id")?.as_str()
+            .ok_or(MigrationError::MissingField("game_id"))?
+            .to_string();
+        games.entry(game_id).or_default().push(event);
+    }
+
+    let mut upgraded = Vec::new();
+    for (game_id, mut events) in games {
+        events.sort_by_key(|event| event.get("sequence").and_then(Value::as_u64));
+        let oldest = events.iter().map(version).min().unwrap_or(SCHEMA_VERSION);
+        for from in oldest..SCHEMA_VERSION {
+            STEPS[(from - 1) as usize](&game_id, &mut events)?;
+        }
+        for mut event in events {
+            // Events written before `schema_version` existed don't have it, even once they're up
+            // to date
+            event["schema_version"] = json!(SCHEMA_VERSION);
+            upgraded.push(serde_json::from_value(event)?);
+        }
+    }
+
+    Ok(upgraded)
+}
+
+/// Version 2 added `scores` to home runs, listing the runners who scored ahead of the batter.
+/// Version 1 events don't say who was on base, so this replays the game to find out.
+fn v1_to_v2(game_id: &str, events: &mut [Value]) -> Result<(), MigrationError> {
+    // Replaying uses the current `Event` and reducer, see the module docs
+    let mut state = State::default();
+    for (expected, event) in (0..).zip(events.iter_mut()) {
+        if field(event, "sequence")?.as_u64() != Some(expected) {
+            return Err(MigrationError::IncompleteGame { game_id: game_id.to_string(), from: 1, expected });
+        }
+
+        if version(event) == 1 {
+            let inner = event.get_mut("event")
+                .and_then(Value::as_object_mut)
+                .ok_or(MigrationError::MissingField("event"))?;
+            if inner.get("type").and_then(Value::as_str) == Some("home_run") {
+                let scores = state.baserunners.iter()
+                    .map(|runner| json!({ "id": runner.id, "name": runner.name }))
+                    .collect();
+                inner.insert("scores".to_string(), Value::Array(scores));
+            }
+            event["schema_version"] = json!(2);
+        }
+
+        let parsed: Event = serde_json::from_value(field(event, "event")?.clone())?;
+        apply(&mut state, &parsed);
+    }
+
+    Ok(())
+}
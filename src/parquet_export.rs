@@ -0,0 +1,120 @@
+//! Writes the `tables` rows as Arrow record batches and Parquet files, for DuckDB, Polars and
+//! friends. Categorical columns (flavors, outcomes, locations, ...) are dictionary-encoded strings.
+
+use std::fs::{self, File};
+use std::path::Path;
+use std::sync::Arc;
+use arrow_array::types::Int32Type;
+use arrow_array::{ArrayRef, BooleanArray, DictionaryArray, Float64Array, Int64Array, RecordBatch, StringArray, UInt64Array};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use uuid::Uuid;
+use crate::tables::{BaserunningRow, PitchRow, PlateAppearanceRow, Tables};
+
+fn uuids<'a>(values: impl Iterator<Item = Option<&'a Uuid>>) -> ArrayRef {
+    Arc::new(values.map(|value| value.map(Uuid::to_string)).collect::<StringArray>())
+}
+
+fn strings<'a>(values: impl Iterator<Item = Option<&'a str>>) -> ArrayRef {
+    Arc::new(values.collect::<StringArray>())
+}
+
+fn categories<'a>(values: impl Iterator<Item = Option<&'a str>>) -> ArrayRef {
+    Arc::new(values.collect::<DictionaryArray<Int32Type>>())
+}
+
+fn ints(values: impl Iterator<Item = Option<i64>>) -> ArrayRef {
+    Arc::new(values.collect::<Int64Array>())
+}
+
+fn bools(values: impl Iterator<Item = bool>) -> ArrayRef {
+    Arc::new(values.map(Some).collect::<BooleanArray>())
+}
+
+fn sequences(values: impl Iterator<Item = u64>) -> ArrayRef {
+    Arc::new(values.map(Some).collect::<UInt64Array>())
+}
+
+pub fn pitches_batch(rows: &[PitchRow]) -> anyhow::Result<RecordBatch> {
+    Ok(RecordBatch::try_from_iter_with_nullable([
+        ("game_id", uuids(rows.iter().map(|row| Some(&row.game_id))), false),
+        ("event_id", uuids(rows.iter().map(|row| Some(&row.event_id))), false),
+        ("sequence", sequences(rows.iter().map(|row| row.sequence)), false),
+        ("inning", ints(rows.iter().map(|row| Some(row.inning))), false),
+        ("top_of_inning", bools(rows.iter().map(|row| row.top_of_inning)), false),
+        ("batter_id", uuids(rows.iter().map(|row| row.batter_id.as_ref())), true),
+        ("batter_name", strings(rows.iter().map(|row| row.batter_name.as_deref())), true),
+        ("pitcher_id", uuids(rows.iter().map(|row| row.pitcher_id.as_ref())), true),
+        ("pitcher_name", strings(rows.iter().map(|row| row.pitcher_name.as_deref())), true),
+        ("balls", ints(rows.iter().map(|row| Some(row.balls))), false),
+        ("strikes", ints(rows.iter().map(|row| Some(row.strikes))), false),
+        ("outs", ints(rows.iter().map(|row| Some(row.outs))), false),
+        ("result", categories(rows.iter().map(|row| Some(row.result))), false),
+        ("flavor", categories(rows.iter().map(|row| row.flavor.as_deref())), true),
+    ])?)
+}
+
+pub fn plate_appearances_batch(rows: &[PlateAppearanceRow]) -> anyhow::Result<RecordBatch> {
+    Ok(RecordBatch::try_from_iter_with_nullable([
+        ("game_id", uuids(rows.iter().map(|row| Some(&row.game_id))), false),
+        ("event_id", uuids(rows.iter().map(|row| Some(&row.event_id))), false),
+        ("sequence", sequences(rows.iter().map(|row| row.sequence)), false),
+        ("inning", ints(rows.iter().map(|row| Some(row.inning))), false),
+        ("top_of_inning", bools(rows.iter().map(|row| row.top_of_inning)), false),
+        ("batter_id", uuids(rows.iter().map(|row| Some(&row.batter_id))), false),
+        ("batter_name", strings(rows.iter().map(|row| Some(row.batter_name.as_str()))), false),
+        ("pitcher_id", uuids(rows.iter().map(|row| row.pitcher_id.as_ref())), true),
+        ("pitcher_name", strings(rows.iter().map(|row| row.pitcher_name.as_deref())), true),
+        ("balls", ints(rows.iter().map(|row| Some(row.balls))), false),
+        ("strikes", ints(rows.iter().map(|row| Some(row.strikes))), false),
+        ("outs", ints(rows.iter().map(|row| Some(row.outs))), false),
+        ("outcome", categories(rows.iter().map(|row| Some(row.outcome))), false),
+        ("hit_type", categories(rows.iter().map(|row| row.hit_type.as_deref())), true),
+        ("location", categories(rows.iter().map(|row| row.location.as_deref())), true),
+        ("fielder_id", uuids(rows.iter().map(|row| row.fielder_id.as_ref())), true),
+        ("fielder_name", strings(rows.iter().map(|row| row.fielder_name.as_deref())), true),
+        ("flavor", categories(rows.iter().map(|row| row.flavor.as_deref())), true),
+        ("runs_scored", Arc::new(rows.iter().map(|row| Some(row.runs_scored)).collect::<Float64Array>()), false),
+    ])?)
+}
+
+pub fn baserunning_batch(rows: &[BaserunningRow]) -> anyhow::Result<RecordBatch> {
+    Ok(RecordBatch::try_from_iter_with_nullable([
+        ("game_id", uuids(rows.iter().map(|row| Some(&row.game_id))), false),
+        ("event_id", uuids(rows.iter().map(|row| Some(&row.event_id))), false),
+        ("sequence", sequences(rows.iter().map(|row| row.sequence)), false),
+        ("inning", ints(rows.iter().map(|row| Some(row.inning))), false),
+        ("top_of_inning", bools(rows.iter().map(|row| row.top_of_inning)), false),
+        ("runner_id", uuids(rows.iter().map(|row| Some(&row.runner_id))), false),
+        ("runner_name", strings(rows.iter().map(|row| Some(row.runner_name.as_str()))), false),
+        ("from_base", ints(rows.iter().map(|row| Some(row.from_base))), false),
+        ("to_base", ints(rows.iter().map(|row| row.to_base)), true),
+        ("outcome", categories(rows.iter().map(|row| Some(row.outcome))), false),
+        ("event_type", categories(rows.iter().map(|row| Some(row.event_type))), false),
+    ])?)
+}
+
+fn write_batch(path: &Path, batch: &RecordBatch) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::ZSTD(ZstdLevel::default()))
+        .build();
+    let mut writer = ArrowWriter::try_new(File::create(path)?, batch.schema(), Some(properties))?;
+    writer.write(batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+/// Writes one game's tables to `<dir>/pitches/<game id>.parquet`,
+/// `<dir>/plate_appearances/<game id>.parquet` and `<dir>/baserunning/<game id>.parquet`,
+/// replacing any that are already there
+pub fn write_game(dir: &Path, game_id: Uuid, tables: &Tables) -> anyhow::Result<()> {
+    let file_name = format!("{game_id}.parquet");
+    write_batch(&dir.join("pitches").join(&file_name), &pitches_batch(&tables.pitches)?)?;
+    write_batch(&dir.join("plate_appearances").join(&file_name), &plate_appearances_batch(&tables.plate_appearances)?)?;
+    write_batch(&dir.join("baserunning").join(&file_name), &baserunning_batch(&tables.baserunning)?)?;
+    Ok(())
+}
@@ -4,11 +4,13 @@ use nom_supreme::error::ErrorTree;
 use nom_supreme::final_parser::{final_parser, Location};
 use nom::Parser as NomParser;
 use nom::sequence::pair;
+use serde::{Deserialize, Serialize};
+use tracing::{trace, trace_span};
 use crate::chron_schema::{GameUpdate, GameUpdateDelta, PlayerDesc, RunnerDesc, State, TeamAtBat};
 use crate::fed_schema::{Advancement, Contact, Event, FailedFielding, Fielding, GroundoutFlavor, HitFlavor, HitType, MaybeFailedFielding, RunnerAdvancement, RunnerAdvancementDesc};
 use crate::text_parsers::*;
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 enum ParserExpectedEvent {
     Invalid,
     #[default]
@@ -25,13 +27,41 @@ enum ParserExpectedEvent {
     PostHitScore(Contact, Fielding, RunnerDesc),
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Parser {
     next_event_genre: ParserExpectedEvent,
     state: State,
     last_update: String,
 }
 
+/// Bump this whenever the serialized form of `Parser` (including everything it can hold, like
+/// `Event`) changes, so that old checkpoints are rejected instead of misread
+const CHECKPOINT_VERSION: u32 = 3;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CheckpointError {
+    #[error("Checkpoint is format version {found}, but this build only reads version {expected}")]
+    UnsupportedVersion { found: u32, expected: u32 },
+    #[error("Malformed checkpoint: {0}")]
+    Malformed(#[from] serde_json::Error),
+}
+
+#[derive(Serialize)]
+struct CheckpointRef<'a> {
+    version: u32,
+    parser: &'a Parser,
+}
+
+#[derive(Deserialize)]
+struct CheckpointHeader {
+    version: u32,
+}
+
+#[derive(Deserialize)]
+struct Checkpoint {
+    parser: Parser,
+}
+
 fn run_parser<'a, T>(
     expression: impl NomParser<&'a str, T, ErrorTree<&'a str>>,
 ) -> impl FnMut(&'a str) -> Result<T, ErrorTree<Location>> {
@@ -40,21 +70,60 @@ fn run_parser<'a, T>(
 
 
 impl Parser {
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    /// Serializes everything the parser needs to pick up where it left off, so an interrupted
+    /// ingest doesn't have to reparse every game from the start
+    pub fn checkpoint(&self) -> Result<Vec<u8>, CheckpointError> {
+        Ok(serde_json::to_vec(&CheckpointRef {
+            version: CHECKPOINT_VERSION,
+            parser: self,
+        })?)
+    }
+
+    pub fn from_checkpoint(checkpoint: &[u8]) -> Result<Self, CheckpointError> {
+        // Check the version on its own first, so a stale checkpoint gets a version error rather
+        // than whatever deserialization error the format change happens to cause
+        let header: CheckpointHeader = serde_json::from_slice(checkpoint)?;
+        if header.version != CHECKPOINT_VERSION {
+            return Err(CheckpointError::UnsupportedVersion {
+                found: header.version,
+                expected: CHECKPOINT_VERSION,
+            });
+        }
+
+        let checkpoint: Checkpoint = serde_json::from_slice(checkpoint)?;
+        Ok(checkpoint.parser)
+    }
+
     pub fn parse(&mut self, delta: GameUpdateDelta) -> anyhow::Result<(Option<Event>, &State)> {
+        let _span = trace_span!("update", display_order = delta.display_order, display_time = %delta.display_time).entered();
+        trace!(text = %delta.display_text, expected = ?self.next_event_genre, "Parsing update");
+
         let prev_state = self.state.clone();
         self.state.update(delta.changed_state);
+        trace!(
+            prev.balls = prev_state.balls, prev.strikes = prev_state.strikes, prev.outs = prev_state.outs,
+            balls = self.state.balls, strikes = self.state.strikes, outs = self.state.outs,
+            "Applied state delta"
+        );
+
         let event = match std::mem::replace(&mut self.next_event_genre, ParserExpectedEvent::Invalid) {
             ParserExpectedEvent::Invalid => {
                 return Err(anyhow!("Parser is in the Invalid state"));
             }
             ParserExpectedEvent::GameStart => {
                 run_parser(tag("Play Ball!"))(&delta.display_text)?;
+                trace!(alternative = "play_ball", "Matched game start");
                 self.next_event_genre = ParserExpectedEvent::BatterUp;
                 Some(Event::PlayBall)
             }
             ParserExpectedEvent::BatterUp => {
                 if self.state.outs == 0 && prev_state.outs > 2 {
                     run_parser(parse_half_inning_end(prev_state.top_of_inning, prev_state.inning))(&delta.display_text)?;
+                    trace!(alternative = "end_of_half_inning", "Outs were reset, so the half-inning ended");
                     self.next_event_genre = ParserExpectedEvent::BatterUp;
                     Some(Event::EndOfHalfInning {
                         top_of_inning: prev_state.top_of_inning,
@@ -65,8 +134,12 @@ impl Parser {
                         .ok_or_else(|| anyhow!("Expected non-null batter in a BatterUp event"))?;
 
                     run_parser(pair(tag(batter.name.as_str()), tag(" steps up to bat.")))(&delta.display_text)?;
+                    trace!(alternative = "batter_up", batter = %batter.name, "Matched batter up");
                     self.next_event_genre = ParserExpectedEvent::Pitch;
-                    Some(Event::BatterUp)
+                    Some(Event::BatterUp {
+                        batter: batter.clone(),
+                        pitcher: self.state.pitcher.clone(),
+                    })
                 }
             }
             ParserExpectedEvent::Pitch => {
@@ -79,7 +152,8 @@ impl Parser {
                     let ball_flavor = run_parser(parse_ball(
                         self.state.balls, self.state.strikes, &pitcher.name, &batter.name,
                     ))(&delta.display_text)?;
-                    self.next_event_genre = ParserExpectedEvent::PostPitchEmpty(Event::Ball(ball_flavor));
+                    trace!(alternative = "ball", flavor = ?ball_flavor, "Balls went up by one, so this is a ball");
+                    self.next_event_genre = ParserExpectedEvent::PostPitchEmpty(Event::Ball { flavor: ball_flavor });
                     None
                 } else if self.state.strikes == prev_state.strikes + 1 {
                     // Strike or Foul event
@@ -90,9 +164,10 @@ impl Parser {
 
                     let parsed = run_parser(parse_strike_or_foul(self.state.balls, self.state.strikes, pitcher, batter))(&delta.display_text)?;
                     let event = match parsed {
-                        ParsedStrikeOrFoul::Strike(flavor) => { Event::Strike(flavor) }
-                        ParsedStrikeOrFoul::Foul(flavor) => { Event::Foul(flavor) }
+                        ParsedStrikeOrFoul::Strike(flavor) => { Event::Strike { flavor } }
+                        ParsedStrikeOrFoul::Foul(flavor) => { Event::Foul { flavor } }
                     };
+                    trace!(alternative = "strike_or_foul", ?event, "Strikes went up by one, so this is a strike or foul");
                     self.next_event_genre = ParserExpectedEvent::PostPitchEmpty(event);
                     None
                 } else if self.state.outs == prev_state.outs + 1 {
@@ -104,6 +179,7 @@ impl Parser {
                         .ok_or_else(|| anyhow!("Expected non-null batter before a Strike event"))?;
 
                     let flavor = run_parser(parse_strikeout(&pitcher.name, &batter.name))(&delta.display_text)?;
+                    trace!(alternative = "strikeout", ?flavor, "Outs went up by one without contact, so this is a strikeout");
                     self.next_event_genre = ParserExpectedEvent::BatterUp;
                     Some(Event::Strikeout {
                         batter: batter.clone(),
@@ -119,10 +195,12 @@ impl Parser {
                     ))(&delta.display_text)?;
                     match parsed {
                         ParsedFoulOrContact::Foul(flavor) => {
-                            self.next_event_genre = ParserExpectedEvent::PostPitchEmpty(Event::Foul(flavor));
+                            trace!(alternative = "foul", ?flavor, "Count unchanged, matched a foul with two strikes");
+                            self.next_event_genre = ParserExpectedEvent::PostPitchEmpty(Event::Foul { flavor });
                             None
                         }
                         ParsedFoulOrContact::Walk(flavor) => {
+                            trace!(alternative = "walk", ?flavor, "Count unchanged, matched a walk");
                             self.next_event_genre = ParserExpectedEvent::BatterUp;
                             Some(Event::Walk {
                                 batter: batter.clone(),
@@ -130,6 +208,7 @@ impl Parser {
                             })
                         }
                         ParsedFoulOrContact::Contact((flavor, location)) => {
+                            trace!(alternative = "contact", ?flavor, ?location, "Count unchanged, matched contact");
                             self.next_event_genre = ParserExpectedEvent::Contact(Contact {
                                 batter: batter.clone(),
                                 flavor,
@@ -142,12 +221,14 @@ impl Parser {
             }
             ParserExpectedEvent::PostPitchEmpty(event) => {
                 run_parser(tag(""))(&delta.display_text)?;
+                trace!(alternative = "post_pitch_empty", "Matched empty update after pitch");
 
                 self.next_event_genre = ParserExpectedEvent::Pitch;
                 Some(event)
             }
             ParserExpectedEvent::PostAppearanceEmpty(event) => {
                 run_parser(tag(""))(&delta.display_text)?;
+                trace!(alternative = "post_appearance_empty", "Matched empty update after plate appearance");
 
                 self.next_event_genre = ParserExpectedEvent::BatterUp;
                 Some(event)
@@ -158,6 +239,7 @@ impl Parser {
                         .ok_or_else(|| anyhow!("Expected non-null defenders after Contact"))?;
 
                     let (defender, flavor) = run_parser(parse_flyout(&defenders))(&delta.display_text)?;
+                    trace!(alternative = "flyout", defender = %defender.name, ?flavor, "Outs went up by one after contact, so this is a flyout");
                     self.next_event_genre = ParserExpectedEvent::BatterUp;
                     Some(Event::Flyout {
                         contact,
@@ -171,13 +253,19 @@ impl Parser {
                     let parsed = run_parser(parse_post_contact(&contact.batter, &defenders))(&delta.display_text)?;
                     match parsed {
                         ParsedPostContact::HomeRun => {
-                            // TODO If there are runners, expect scores
+                            trace!(alternative = "home_run", "Matched home run after contact");
+                            // Everyone on base scores
+                            let scores = prev_state.baserunners.iter()
+                                .map(|runner| PlayerDesc { id: runner.id, name: runner.name.clone(), extra: Default::default() })
+                                .collect();
                             self.next_event_genre = ParserExpectedEvent::PostAppearanceEmpty(Event::HomeRun {
                                 contact,
+                                scores,
                             });
                             None
                         }
                         ParsedPostContact::Fielding(defender, flavor) => {
+                            trace!(alternative = "fielding", defender = %defender.name, ?flavor, "Matched fielding after contact");
                             self.next_event_genre = ParserExpectedEvent::Fielding(contact, Fielding {
                                 defender,
                                 flavor,
@@ -185,6 +273,7 @@ impl Parser {
                             None
                         }
                         ParsedPostContact::FailedFielding(defender, flavor) => {
+                            trace!(alternative = "failed_fielding", defender = %defender.name, ?flavor, "Matched failed fielding after contact");
                             self.next_event_genre = ParserExpectedEvent::FailedFielding(contact, FailedFielding {
                                 defender,
                                 flavor,
@@ -199,6 +288,7 @@ impl Parser {
                 let parsed = run_parser(parse_fielding_result(&contact.batter, &fielding.defender, &baserunners_before))(&delta.display_text)?;
                 match parsed {
                     FieldingResult::Groundout(flavor) => {
+                        trace!(alternative = "groundout", ?flavor, baserunners = baserunners_before.len(), outs = self.state.outs, "Matched groundout");
                         if baserunners_before.is_empty() || self.state.outs > 2 {
                             self.next_event_genre = ParserExpectedEvent::BatterUp;
                             Some(Event::GroundOut {
@@ -213,15 +303,18 @@ impl Parser {
                         }
                     }
                     FieldingResult::Hit((hit_type, flavor)) => {
+                        trace!(alternative = "hit", ?hit_type, ?flavor, "Matched hit after fielding");
                         self.emit_hit(contact, fielding, hit_type, flavor, baserunners_before)
                     }
                     FieldingResult::ForceOut(runner) => {
+                        trace!(alternative = "force_out", runner = %runner.name, base = runner.base, "Matched force out");
                         let runner_out = runner.clone();
                         drop(parsed);
                         self.next_event_genre = ParserExpectedEvent::PostForceOut(contact, fielding, runner_out);
                         None
                     }
                     FieldingResult::Score(runner) => {
+                        trace!(alternative = "score", runner = %runner.name, base = runner.base, "Matched score after fielding");
                         let scoring_runner = runner.clone();
                         drop(parsed);
                         self.next_event_genre = ParserExpectedEvent::PostHitScore(contact, fielding, scoring_runner);
@@ -231,6 +324,7 @@ impl Parser {
             }
             ParserExpectedEvent::FailedFielding(contact, fielding, baserunners_before) => {
                 let (hit_type, flavor) = run_parser(parse_base_hit(&contact.batter))(&delta.display_text)?;
+                trace!(alternative = "hit", ?hit_type, ?flavor, "Matched hit after failed fielding");
                 self.emit_hit(contact, fielding, hit_type, flavor, baserunners_before)
             }
             ParserExpectedEvent::PostGroundOut(contact, fielding, flavor, mut advancements, baserunners_before) => {
@@ -239,6 +333,7 @@ impl Parser {
                 let parsed = run_parser(parse_post_ground_out(last_runner))(&delta.display_text)?;
                 match parsed {
                     ParsedPostGroundOut::Advances((to_base, flavor)) => {
+                        trace!(alternative = "advances", runner = %last_runner.name, ?to_base, ?flavor, "Matched runner advancing after groundout");
                         advancements.push(RunnerAdvancementDesc {
                             runner: last_runner.clone(),
                             advancement: RunnerAdvancement::Advanced(to_base, flavor),
@@ -262,6 +357,7 @@ impl Parser {
             }
             ParserExpectedEvent::PostForceOut(contact, fielding, runner_out) => {
                 run_parser(tag("Fielder's choice."))(&delta.display_text)?;
+                trace!(alternative = "fielders_choice", "Matched fielder's choice");
                 self.next_event_genre = ParserExpectedEvent::BatterUp;
                 Some(Event::FieldersChoice {
                     contact,
@@ -271,6 +367,7 @@ impl Parser {
             }
             ParserExpectedEvent::PostHitScore(contact, fielding, scoring_runner) => {
                 let (hit_type, flavor) = run_parser(parse_base_hit(&contact.batter))(&delta.display_text)?;
+                trace!(alternative = "hit", ?hit_type, ?flavor, "Matched hit after runner scored");
                 self.next_event_genre = ParserExpectedEvent::BatterUp;
                 Some(Event::Hit {
                     contact,
@@ -281,11 +378,13 @@ impl Parser {
                     scores: vec![PlayerDesc {
                         id: scoring_runner.id,
                         name: scoring_runner.name,
+                        extra: Default::default(),
                     }],
                 })
             }
         };
 
+        trace!(next = ?self.next_event_genre, ?event, "Finished update");
         Ok((event, &self.state))
     }
 
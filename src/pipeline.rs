@@ -0,0 +1,589 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinSet;
+use tokio::time::Instant;
+use tracing::{debug, info, info_span, warn};
+use uuid::Uuid;
+use crate::chron_schema::{ExtraFields, GameResponse, GameUpdateDelta, State};
+use crate::event_source::{EventSource, TimestampBatches};
+use crate::fed_schema::{FedEvent, SCHEMA_VERSION};
+use crate::parser::{CheckpointError, Parser};
+use crate::reorder::{Released, ReorderBuffer};
+use crate::reducer::ends_game;
+use crate::validator::validate;
+
+/// How many batches the fetcher can get ahead of the demultiplexer
+const BATCH_CHANNEL_SIZE: usize = 4;
+/// How many updates a game's worker can fall behind before the demultiplexer waits for it
+const GAME_CHANNEL_SIZE: usize = 1024;
+/// How many parsed updates can be waiting to be written out
+const OUTPUT_CHANNEL_SIZE: usize = 1024;
+
+const INGEST_CHECKPOINT_VERSION: u32 = 3;
+
+/// Where the event currently being parsed started
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct EventStart {
+    // The state before the event, to validate it against
+    state: State,
+    timestamp: DateTime<Utc>,
+    display_time: DateTime<Utc>,
+    display_order: i64,
+}
+
+/// Everything needed to parse one game's updates, which have to be fed in order
+pub struct GameIngest {
+    game_id: Uuid,
+    parser: Parser,
+    // Lines of the event currently being parsed, to check against the lines Fed generates for it
+    pending_lines: Vec<String>,
+    // Fields Fed doesn't know about from the updates of the event currently being parsed
+    pending_extra: ExtraFields,
+    event_start: Option<EventStart>,
+    next_sequence: u64,
+    over: bool,
+}
+
+/// A Fed event, along with every field Fed didn't know about in the updates it was parsed from.
+/// The fields are keyed by their paths in the chronicler item, as in `GameResponse::unknown_fields`,
+/// with later updates winning if more than one has the same field.
+///
+/// Serializes as the event with the fields under `extra`, left out if there aren't any.
+#[derive(Debug, Serialize)]
+pub struct ParsedEvent {
+    #[serde(flatten)]
+    pub event: FedEvent,
+    #[serde(skip_serializing_if = "ExtraFields::is_empty")]
+    pub extra: ExtraFields,
+}
+
+impl GameIngest {
+    pub fn new(game_id: Uuid) -> Self {
+        Self {
+            game_id,
+            parser: Parser::default(),
+            pending_lines: Vec::new(),
+            pending_extra: ExtraFields::new(),
+            event_start: None,
+            next_sequence: 0,
+            over: false,
+        }
+    }
+
+    /// Whether the last event ingested ended the game, after which there's nothing more to parse
+    pub fn is_over(&self) -> bool {
+        self.over
+    }
+
+    /// Feeds the game's next update to its parser, returning the Fed event if it completed one.
+    /// Fails if the lines the event was parsed from aren't the ones Fed would write for it.
+    pub fn ingest(&mut self, item: &GameResponse) -> anyhow::Result<Option<ParsedEvent>> {
+        let update = &item.data;
+        let event_start = self.event_start.get_or_insert_with(|| EventStart {
+            state: self.parser.state().clone(),
+            timestamp: item.timestamp,
+            display_time: update.display_time,
+            display_order: update.display_order,
+        });
+        let (parsed, state) = self.parser.parse(update.clone())?;
+
+        self.pending_lines.push(update.display_text.clone());
+        self.pending_extra.extend(item.extra_fields().map(|(field, value)| (field, value.clone())));
+        let Some(parsed) = parsed else {
+            return Ok(None);
+        };
+
+        let reconstructed_description = parsed.lines(state)?;
+        if self.pending_lines != reconstructed_description {
+            return Err(anyhow!(
+                "{parsed:?} was parsed from {:?}, but Fed would describe it as {:?}",
+                self.pending_lines, reconstructed_description,
+            ));
+        }
+        for discrepancy in validate(&parsed, &event_start.state, state) {
+            warn!(game_id = %self.game_id, event = ?parsed, "{discrepancy}");
+        }
+        self.pending_lines.clear();
+        self.over = ends_game(&parsed, state);
+
+        let fed_event = FedEvent {
+            schema_version: SCHEMA_VERSION,
+            id: FedEvent::id_for(self.game_id, event_start.display_order),
+            game_id: self.game_id,
+            sequence: self.next_sequence,
+            timestamp: event_start.timestamp,
+            display_time: event_start.display_time,
+            first_display_order: event_start.display_order,
+            last_display_order: update.display_order,
+            event: parsed,
+        };
+        self.event_start = None;
+        self.next_sequence += 1;
+        Ok(Some(ParsedEvent { event: fed_event, extra: std::mem::take(&mut self.pending_extra) }))
+    }
+}
+
+/// The result of feeding one update to its game's parser
+#[derive(Debug)]
+pub struct GameOutput {
+    pub game_id: Uuid,
+    pub timestamp: DateTime<Utc>,
+    pub input: GameUpdateDelta,
+    /// Fields of the chronicler item that Fed doesn't know about
+    pub extra: ExtraFields,
+    /// The event this update completed, if it completed one
+    pub event: Option<ParsedEvent>,
+}
+
+/// Where `run` sends each update's result
+pub trait OutputSink: Send + 'static {
+    fn write(&mut self, output: GameOutput) -> anyhow::Result<()>;
+
+    /// How much has been written so far, which checkpoints save. Sinks that can't go back to an
+    /// earlier position return `None`, and a run resumed from a checkpoint then repeats whatever
+    /// they were sent after it.
+    fn position(&mut self) -> anyhow::Result<Option<u64>> {
+        Ok(None)
+    }
+
+    /// Throws away everything written since `position` was returned. Called before anything is
+    /// written when resuming from a checkpoint that saved a position.
+    fn rewind(&mut self, _position: u64) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+impl<F> OutputSink for F where F: FnMut(GameOutput) -> anyhow::Result<()> + Send + 'static {
+    fn write(&mut self, output: GameOutput) -> anyhow::Result<()> {
+        self(output)
+    }
+}
+
+/// How `run` saves its progress so a later run can carry on from it
+#[derive(Debug, Clone)]
+pub struct CheckpointOptions {
+    pub path: PathBuf,
+    /// How long to go between checkpoints. Taking one briefly pauses the pipeline.
+    pub interval: std::time::Duration,
+    /// Carry on from the checkpoint at `path` instead of starting over
+    pub resume: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct PipelineOptions {
+    /// The window for each game's `ReorderBuffer`
+    pub reorder_window: Duration,
+    pub checkpoint: Option<CheckpointOptions>,
+}
+
+/// One game's worker, as saved in a checkpoint
+#[derive(Serialize, Deserialize)]
+struct GameCheckpoint {
+    parser: serde_json::Value,
+    pending_lines: Vec<String>,
+    pending_extra: ExtraFields,
+    event_start: Option<EventStart>,
+    next_sequence: u64,
+    reorder_buffer: ReorderBuffer,
+}
+
+/// Everything needed to carry on exactly where a run left off: where the source was up to, the
+/// items it had returned that hadn't been handed to a worker yet, every game's worker, and how
+/// much output had been written
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "C: Serialize + DeserializeOwned")]
+struct IngestCheckpoint<C> {
+    version: u32,
+    cursor: C,
+    held_back: Vec<GameResponse>,
+    games: HashMap<Uuid, GameCheckpoint>,
+    output_position: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct IngestCheckpointHeader {
+    version: u32,
+}
+
+impl<C: Serialize + DeserializeOwned> IngestCheckpoint<C> {
+    fn load(path: &Path) -> anyhow::Result<Self> {
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("Couldn't read checkpoint {}", path.display()))?;
+        // Same as `Parser::from_checkpoint`, check the version before anything else can fail
+        let header: IngestCheckpointHeader = serde_json::from_slice(&bytes)
+            .map_err(CheckpointError::from)?;
+        if header.version != INGEST_CHECKPOINT_VERSION {
+            return Err(CheckpointError::UnsupportedVersion {
+                found: header.version,
+                expected: INGEST_CHECKPOINT_VERSION,
+            }.into());
+        }
+
+        Ok(serde_json::from_slice(&bytes).map_err(CheckpointError::from)?)
+    }
+
+    fn save(&self, path: &Path) -> anyhow::Result<()> {
+        // Write then rename, so dying partway through never leaves a broken checkpoint behind
+        let temp_path = path.with_extension("tmp");
+        std::fs::write(&temp_path, serde_json::to_vec(self)?)
+            .with_context(|| format!("Couldn't write checkpoint {}", temp_path.display()))?;
+        std::fs::rename(&temp_path, path)
+            .with_context(|| format!("Couldn't write checkpoint {}", path.display()))?;
+        debug!(path = %path.display(), games = self.games.len(), "Saved checkpoint");
+        Ok(())
+    }
+}
+
+/// A game's parser and the reorder buffer in front of it
+struct GameWorker {
+    ingest: GameIngest,
+    reorder_buffer: ReorderBuffer,
+}
+
+impl GameWorker {
+    fn new(game_id: Uuid, reorder_window: Duration) -> Self {
+        Self {
+            ingest: GameIngest::new(game_id),
+            reorder_buffer: ReorderBuffer::new(reorder_window),
+        }
+    }
+
+    fn from_checkpoint(game_id: Uuid, checkpoint: GameCheckpoint, reorder_window: Duration) -> anyhow::Result<Self> {
+        let parser = Parser::from_checkpoint(&serde_json::to_vec(&checkpoint.parser)?)
+            .with_context(|| format!("Couldn't restore the parser for game {game_id}"))?;
+        Ok(Self {
+            ingest: GameIngest {
+                game_id,
+                parser,
+                pending_lines: checkpoint.pending_lines,
+                pending_extra: checkpoint.pending_extra,
+                event_start: checkpoint.event_start,
+                next_sequence: checkpoint.next_sequence,
+                over: false,
+            },
+            reorder_buffer: checkpoint.reorder_buffer.with_window(reorder_window),
+        })
+    }
+
+    fn checkpoint(&self) -> anyhow::Result<GameCheckpoint> {
+        Ok(GameCheckpoint {
+            parser: serde_json::from_slice(&self.ingest.parser.checkpoint()?)?,
+            pending_lines: self.ingest.pending_lines.clone(),
+            pending_extra: self.ingest.pending_extra.clone(),
+            event_start: self.ingest.event_start.clone(),
+            next_sequence: self.ingest.next_sequence,
+            reorder_buffer: self.reorder_buffer.clone(),
+        })
+    }
+}
+
+/// A batch of items from the source, along with where the source was up to once it returned them
+struct Batch<C> {
+    items: Vec<GameResponse>,
+    cursor: C,
+    held_back: Vec<GameResponse>,
+}
+
+enum WorkerMessage {
+    Update(Box<GameResponse>),
+    /// Asks the worker for its checkpoint, once it's dealt with every update sent before this.
+    /// Games that are over don't have one.
+    Checkpoint(oneshot::Sender<anyhow::Result<Option<GameCheckpoint>>>),
+}
+
+enum Output<C> {
+    Game(Box<GameOutput>),
+    /// Everything before this in the output channel is covered by the checkpoint, and nothing
+    /// after it is
+    Checkpoint(Box<IngestCheckpoint<C>>),
+}
+
+/// Parses every game from `source`, passing each update's result to `sink` as soon as it's
+/// ready.
+///
+/// Fetching, demultiplexing by game, parsing and writing all run concurrently, connected by
+/// bounded channels so no stage can get too far ahead. Each game gets its own worker, so updates
+/// within a game are parsed in order while different games are parsed in parallel. Before
+/// parsing, each game's updates go through a `ReorderBuffer`. `sink` sees each game's outputs in
+/// order, but outputs from different games can be interleaved. Once a game is over, its worker
+/// stops and is forgotten, so a run that tails chronicler for a long time only keeps workers for
+/// games in progress. Any updates for a game after it's over are skipped.
+///
+/// With checkpointing on, every so often the demultiplexer stops handing out items until every
+/// worker has caught up and sent back its state. The checkpoint then goes down the output channel
+/// behind everything the workers wrote before it, and is saved along with the sink's position
+/// once those have been written. Resuming rewinds the sink to that position before carrying on,
+/// so the combined output is the same as a run that was never interrupted. A sink without a
+/// position gets whatever the original run sent it after the checkpoint again.
+pub async fn run<S, W>(source: S, options: PipelineOptions, mut sink: W) -> anyhow::Result<()>
+    where S: EventSource + Send + 'static,
+          W: OutputSink {
+    let mut source = source;
+    let mut held_back = Vec::new();
+    let mut restored_games = HashMap::new();
+    if let Some(checkpoint_options) = options.checkpoint.as_ref().filter(|options| options.resume) {
+        let checkpoint = IngestCheckpoint::<S::Cursor>::load(&checkpoint_options.path)?;
+        info!(path = %checkpoint_options.path.display(), games = checkpoint.games.len(), "Resuming from checkpoint");
+        source.resume(checkpoint.cursor)?;
+        if let Some(position) = checkpoint.output_position {
+            sink.rewind(position)?;
+        }
+        held_back = checkpoint.held_back;
+        restored_games = checkpoint.games;
+    }
+    let mut batches = TimestampBatches::resume(source, held_back);
+
+    let (batch_tx, mut batch_rx) = mpsc::channel(BATCH_CHANNEL_SIZE);
+    let (output_tx, mut output_rx) = mpsc::channel(OUTPUT_CHANNEL_SIZE);
+
+    let mut tasks: JoinSet<anyhow::Result<()>> = JoinSet::new();
+    tasks.spawn(async move {
+        loop {
+            let items = batches.next_items().await?;
+            let exhausted = items.is_none();
+            let batch = Batch {
+                items: items.unwrap_or_default(),
+                cursor: batches.source().cursor(),
+                held_back: batches.held_back().to_vec(),
+            };
+            // An empty batch at the end hands over the final cursor
+            if batch_tx.send(batch).await.is_err() || exhausted {
+                // Either finished, or the pipeline is shutting down because something else failed
+                break;
+            }
+        }
+        Ok(())
+    });
+
+    let checkpoint_path = options.checkpoint.as_ref().map(|options| options.path.clone());
+    // Returns the sink's final position, for the checkpoint saved at the end
+    let writer = tokio::spawn(async move {
+        while let Some(output) = output_rx.recv().await {
+            match output {
+                Output::Game(output) => { sink.write(*output)?; }
+                Output::Checkpoint(mut checkpoint) => {
+                    if let Some(path) = &checkpoint_path {
+                        checkpoint.output_position = sink.position()?;
+                        checkpoint.save(path)?;
+                    }
+                }
+            }
+        }
+        sink.position()
+    });
+
+    let mut game_senders: HashMap<Uuid, mpsc::Sender<WorkerMessage>> = HashMap::new();
+    let mut workers = JoinSet::new();
+    // Workers say when their game is over, and the demultiplexer then drops their channel
+    let (over_tx, mut over_rx) = mpsc::unbounded_channel();
+    for (game_id, checkpoint) in restored_games {
+        let worker = GameWorker::from_checkpoint(game_id, checkpoint, options.reorder_window)?;
+        game_senders.insert(game_id, spawn_worker(&mut workers, worker, output_tx.clone(), over_tx.clone()));
+    }
+
+    let mut last_checkpoint = Instant::now();
+    let demux = async {
+        let mut final_batch = None;
+        // Only the ids of games that are over are kept, so late updates for them can be skipped
+        let mut over_games = HashSet::new();
+        while let Some(batch) = batch_rx.recv().await {
+            // Don't keep feeding the other games if one has already failed
+            while let Some(result) = workers.try_join_next() {
+                result??;
+            }
+
+            let Batch { items, cursor, held_back } = batch;
+            for item in items {
+                while let Ok(game_id) = over_rx.try_recv() {
+                    // Lets the worker finish once it's dealt with everything already sent to it
+                    game_senders.remove(&game_id);
+                    over_games.insert(game_id);
+                }
+                if over_games.contains(&item.game_id) {
+                    debug!(game_id = %item.game_id, display_order = item.data.display_order, "Skipping an update from after the game was over");
+                    continue;
+                }
+
+                let sender = game_senders.entry(item.game_id)
+                    .or_insert_with(|| {
+                        let worker = GameWorker::new(item.game_id, options.reorder_window);
+                        spawn_worker(&mut workers, worker, output_tx.clone(), over_tx.clone())
+                    });
+                if sender.send(WorkerMessage::Update(Box::new(item))).await.is_err() {
+                    // The worker only stops early if it failed, which joining it will report
+                    return Ok(None);
+                }
+            }
+
+            let checkpoint_due = options.checkpoint.as_ref()
+                .is_some_and(|options| last_checkpoint.elapsed() >= options.interval);
+            if checkpoint_due {
+                let Some(games) = checkpoint_workers(&game_senders).await? else {
+                    return Ok(None);
+                };
+                let checkpoint = IngestCheckpoint {
+                    version: INGEST_CHECKPOINT_VERSION,
+                    cursor: cursor.clone(),
+                    held_back: held_back.clone(),
+                    games,
+                    // Filled in by the writer once it's caught up
+                    output_position: None,
+                };
+                if output_tx.send(Output::Checkpoint(Box::new(checkpoint))).await.is_err() {
+                    return Ok(None);
+                }
+                last_checkpoint = Instant::now();
+            }
+            final_batch = Some((cursor, held_back));
+        }
+        Ok::<_, anyhow::Error>(final_batch)
+    };
+    let demux_result = demux.await;
+    // Closing the game channels lets the workers finish, and once they have, the output channel
+    // closes and lets the writer finish. If the demultiplexer stopped early, closing the batch
+    // channel stops the fetcher waiting to hand it more.
+    drop(game_senders);
+    drop(output_tx);
+    drop(batch_rx);
+
+    let final_batch = demux_result?;
+    let mut games = HashMap::new();
+    while let Some(result) = workers.join_next().await {
+        if let (game_id, Some(checkpoint)) = result?? {
+            games.insert(game_id, checkpoint);
+        }
+    }
+    while let Some(result) = tasks.join_next().await {
+        result??;
+    }
+    let output_position = writer.await??;
+
+    // Everything's been written, so save where the run ended up in case there's more to come
+    if let (Some(options), Some((cursor, held_back))) = (&options.checkpoint, final_batch) {
+        IngestCheckpoint {
+            version: INGEST_CHECKPOINT_VERSION,
+            cursor,
+            held_back,
+            games,
+            output_position,
+        }.save(&options.path)?;
+    }
+
+    Ok(())
+}
+
+/// Collects a checkpoint from every worker whose game isn't over. Returns `None` if a worker has
+/// stopped, which can only happen if it failed.
+async fn checkpoint_workers(game_senders: &HashMap<Uuid, mpsc::Sender<WorkerMessage>>) -> anyhow::Result<Option<HashMap<Uuid, GameCheckpoint>>> {
+    let mut replies = Vec::with_capacity(game_senders.len());
+    for (&game_id, sender) in game_senders {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        if sender.send(WorkerMessage::Checkpoint(reply_tx)).await.is_err() {
+            return Ok(None);
+        }
+        replies.push((game_id, reply_rx));
+    }
+
+    let mut games = HashMap::with_capacity(replies.len());
+    for (game_id, reply_rx) in replies {
+        let Ok(checkpoint) = reply_rx.await else {
+            return Ok(None);
+        };
+        if let Some(checkpoint) = checkpoint? {
+            games.insert(game_id, checkpoint);
+        }
+    }
+
+    Ok(Some(games))
+}
+
+/// Spawns a worker for a game. It sends the game's id to `over_tx` once the game is over, and
+/// finishes once the returned sender is dropped, returning its checkpoint unless the game is over.
+fn spawn_worker<C: Send + 'static>(
+    workers: &mut JoinSet<anyhow::Result<(Uuid, Option<GameCheckpoint>)>>,
+    mut worker: GameWorker,
+    output_tx: mpsc::Sender<Output<C>>,
+    over_tx: mpsc::UnboundedSender<Uuid>,
+) -> mpsc::Sender<WorkerMessage> {
+    let (tx, mut rx) = mpsc::channel(GAME_CHANNEL_SIZE);
+    workers.spawn(async move {
+        let game_id = worker.ingest.game_id;
+        while let Some(message) = rx.recv().await {
+            match message {
+                WorkerMessage::Update(item) => {
+                    let was_over = worker.ingest.is_over();
+                    let released = worker.reorder_buffer.push(*item);
+                    if !process(&mut worker.ingest, released, &output_tx).await? {
+                        break;
+                    }
+                    if worker.ingest.is_over() && !was_over {
+                        debug!(%game_id, "Game is over");
+                        // If the demultiplexer has gone, the pipeline is already shutting down
+                        let _ = over_tx.send(game_id);
+                    }
+                }
+                WorkerMessage::Checkpoint(reply_tx) => {
+                    let checkpoint = if worker.ingest.is_over() {
+                        Ok(None)
+                    } else {
+                        worker.checkpoint().map(Some)
+                    };
+                    // If nobody's waiting for it, the pipeline is already shutting down
+                    let _ = reply_tx.send(checkpoint);
+                }
+            }
+        }
+
+        if worker.ingest.is_over() {
+            return Ok((game_id, None));
+        }
+        let released = worker.reorder_buffer.flush();
+        process(&mut worker.ingest, released, &output_tx).await?;
+        Ok((game_id, Some(worker.checkpoint()?)))
+    });
+
+    tx
+}
+
+/// Parses updates released by a game's `ReorderBuffer` and sends on the results. Returns false if
+/// nothing is listening for the results any more.
+async fn process<C>(game: &mut GameIngest, released: Vec<Released>, output_tx: &mpsc::Sender<Output<C>>) -> anyhow::Result<bool> {
+    for released in released {
+        if game.is_over() {
+            if let Released::Update(item) = released {
+                debug!(game_id = %game.game_id, display_order = item.data.display_order, "Skipping an update from after the game was over");
+            }
+            continue;
+        }
+        let item = match released {
+            Released::Update(item) => { *item }
+            Released::Gap(display_orders) => {
+                warn!(game_id = %game.game_id, ?display_orders, "Updates never arrived, parsing without them");
+                continue;
+            }
+        };
+
+        let event = {
+            let _span = info_span!("game", game_id = %item.game_id, timestamp = %item.timestamp).entered();
+            game.ingest(&item)
+                .with_context(|| format!("Couldn't parse game {} at display order {}", item.game_id, item.data.display_order))?
+        };
+        let output = GameOutput {
+            game_id: item.game_id,
+            timestamp: item.timestamp,
+            input: item.data,
+            extra: item.extra,
+            event,
+        };
+        if output_tx.send(Output::Game(Box::new(output))).await.is_err() {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
@@ -0,0 +1,857 @@
+//! Protobuf encoding of Fed events, for shipping them between services more compactly than JSON.
+//!
+//! The messages are generated from `proto/fed.proto` when building, so they can't drift from it.
+//! Convert from the `fed_schema` types with `From`, and back with `to_fed`, which fails on anything
+//! the Fed types can't represent, like a missing field or an unknown enum value.
+//!
+//! Streams of events are length-delimited: see `write_event` and `EventReader`.
+
+use std::io::{self, BufRead, Write};
+use anyhow::anyhow;
+use chrono::{DateTime, TimeZone, Utc};
+use prost::Message;
+use uuid::Uuid;
+use crate::chron_schema;
+use crate::fed_schema;
+
+/// Converts a decoded message or enum back into the Fed type it mirrors
+trait IntoFed<T> {
+    fn into_fed(self) -> anyhow::Result<T>;
+}
+
+/// Message fields are always optional in proto3, but the Fed ones aren't
+impl<P: IntoFed<T>, T> IntoFed<T> for Option<P> {
+    fn into_fed(self) -> anyhow::Result<T> {
+        self.ok_or_else(|| anyhow!("Protobuf message is missing a required field"))?.into_fed()
+    }
+}
+
+impl<P: IntoFed<T>, T> IntoFed<Vec<T>> for Vec<P> {
+    fn into_fed(self) -> anyhow::Result<Vec<T>> {
+        self.into_iter().map(IntoFed::into_fed).collect()
+    }
+}
+
+fn uuid(bytes: &[u8]) -> anyhow::Result<Uuid> {
+    Ok(Uuid::from_slice(bytes)?)
+}
+
+fn timestamp(time: DateTime<Utc>) -> prost_types::Timestamp {
+    prost_types::Timestamp {
+        seconds: time.timestamp(),
+        nanos: time.timestamp_subsec_nanos() as i32,
+    }
+}
+
+impl IntoFed<DateTime<Utc>> for prost_types::Timestamp {
+    fn into_fed(self) -> anyhow::Result<DateTime<Utc>> {
+        Utc.timestamp_opt(self.seconds, self.nanos as u32).single()
+            .ok_or_else(|| anyhow!("Timestamp {}.{:09} is out of range", self.seconds, self.nanos))
+    }
+}
+
+// The messages, generated from `proto/fed.proto` by `build.rs`
+include!(concat!(env!("OUT_DIR"), "/fed.rs"));
+
+/// Conversions between a generated enum and the `fed_schema` enum of the same name, which has the
+/// same variants apart from `Unspecified`. Enum fields are stored as `i32`, and prost generates a
+/// getter on the message that turns unknown values into `Unspecified`.
+macro_rules! mirrored_enumeration {
+    ($name:ident { $($variant:ident,)* }) => {
+        impl From<fed_schema::$name> for $name {
+            fn from(value: fed_schema::$name) -> Self {
+                match value {
+                    $(fed_schema::$name::$variant => { $name::$variant })*
+                }
+            }
+        }
+
+        impl IntoFed<fed_schema::$name> for $name {
+            fn into_fed(self) -> anyhow::Result<fed_schema::$name> {
+                match self {
+                    $($name::$variant => { Ok(fed_schema::$name::$variant) })*
+                    $name::Unspecified => { Err(anyhow!(concat!("Unspecified or unknown ", stringify!($name)))) }
+                }
+            }
+        }
+    };
+}
+
+impl From<&fed_schema::FedEvent> for FedEvent {
+    fn from(event: &fed_schema::FedEvent) -> Self {
+        FedEvent {
+            schema_version: event.schema_version,
+            id: event.id.as_bytes().to_vec(),
+            game_id: event.game_id.as_bytes().to_vec(),
+            sequence: event.sequence,
+            timestamp: Some(timestamp(event.timestamp)),
+            display_time: Some(timestamp(event.display_time)),
+            first_display_order: event.first_display_order,
+            last_display_order: event.last_display_order,
+            event: Some(Event::from(&event.event)),
+        }
+    }
+}
+
+impl FedEvent {
+    pub fn to_fed(self) -> anyhow::Result<fed_schema::FedEvent> {
+        self.into_fed()
+    }
+}
+
+impl IntoFed<fed_schema::FedEvent> for FedEvent {
+    fn into_fed(self) -> anyhow::Result<fed_schema::FedEvent> {
+        Ok(fed_schema::FedEvent {
+            schema_version: self.schema_version,
+            id: uuid(&self.id)?,
+            game_id: uuid(&self.game_id)?,
+            sequence: self.sequence,
+            timestamp: self.timestamp.into_fed()?,
+            display_time: self.display_time.into_fed()?,
+            first_display_order: self.first_display_order,
+            last_display_order: self.last_display_order,
+            event: self.event.into_fed()?,
+        })
+    }
+}
+
+impl From<&fed_schema::Event> for Event {
+    fn from(value: &fed_schema::Event) -> Self {
+        use event::*;
+
+        let kind = match value {
+            fed_schema::Event::PlayBall => {
+                Kind::PlayBall(PlayBall {})
+            }
+            fed_schema::Event::BatterUp { batter, pitcher } => {
+                Kind::BatterUp(BatterUp { batter: Some(batter.into()), pitcher: pitcher.as_ref().map(Into::into) })
+            }
+            fed_schema::Event::Ball { flavor } => {
+                Kind::Ball(Ball { flavor: Some((*flavor).into()) })
+            }
+            fed_schema::Event::Strike { flavor } => {
+                Kind::Strike(Strike { flavor: Some((*flavor).into()) })
+            }
+            fed_schema::Event::Flyout { contact, defender, flavor } => {
+                Kind::Flyout(Flyout {
+                    contact: Some(contact.into()),
+                    defender: Some(defender.into()),
+                    flavor: Some((*flavor).into()),
+                })
+            }
+            fed_schema::Event::GroundOut { contact, fielding, flavor, advancements } => {
+                Kind::GroundOut(GroundOut {
+                    contact: Some(contact.into()),
+                    fielding: Some(fielding.into()),
+                    flavor: GroundoutFlavor::from(*flavor).into(),
+                    advancements: advancements.iter().map(Into::into).collect(),
+                })
+            }
+            fed_schema::Event::Strikeout { batter, flavor } => {
+                Kind::Strikeout(Strikeout { batter: Some(batter.into()), flavor: StrikeoutFlavor::from(*flavor).into() })
+            }
+            fed_schema::Event::Foul { flavor } => {
+                Kind::Foul(Foul { flavor: FoulFlavor::from(*flavor).into() })
+            }
+            fed_schema::Event::HomeRun { contact, scores } => {
+                Kind::HomeRun(HomeRun {
+                    contact: Some(contact.into()),
+                    scores: scores.iter().map(Into::into).collect(),
+                })
+            }
+            fed_schema::Event::Hit { contact, fielding, hit_type, flavor, advancements, scores } => {
+                Kind::Hit(Hit {
+                    contact: Some(contact.into()),
+                    fielding: Some(fielding.into()),
+                    hit_type: HitType::from(*hit_type).into(),
+                    flavor: HitFlavor::from(*flavor).into(),
+                    advancements: advancements.iter().map(Into::into).collect(),
+                    scores: scores.iter().map(Into::into).collect(),
+                })
+            }
+            fed_schema::Event::Walk { batter, flavor } => {
+                Kind::Walk(Walk { batter: Some(batter.into()), flavor: WalkFlavor::from(*flavor).into() })
+            }
+            fed_schema::Event::EndOfHalfInning { top_of_inning, inning } => {
+                Kind::EndOfHalfInning(EndOfHalfInning { top_of_inning: *top_of_inning, inning: *inning })
+            }
+            fed_schema::Event::FieldersChoice { contact, fielding, runner_out } => {
+                Kind::FieldersChoice(FieldersChoice {
+                    contact: Some(contact.into()),
+                    fielding: Some(fielding.into()),
+                    runner_out: Some(runner_out.into()),
+                })
+            }
+        };
+
+        Event { kind: Some(kind) }
+    }
+}
+
+impl IntoFed<fed_schema::Event> for Event {
+    fn into_fed(self) -> anyhow::Result<fed_schema::Event> {
+        use event::*;
+
+        let kind = self.kind.ok_or_else(|| anyhow!("Event has no kind"))?;
+        Ok(match kind {
+            Kind::PlayBall(PlayBall {}) => {
+                fed_schema::Event::PlayBall
+            }
+            Kind::BatterUp(e) => {
+                fed_schema::Event::BatterUp { batter: e.batter.into_fed()?, pitcher: e.pitcher.map(IntoFed::into_fed).transpose()? }
+            }
+            Kind::Ball(e) => {
+                fed_schema::Event::Ball { flavor: e.flavor.into_fed()? }
+            }
+            Kind::Strike(e) => {
+                fed_schema::Event::Strike { flavor: e.flavor.into_fed()? }
+            }
+            Kind::Flyout(e) => {
+                fed_schema::Event::Flyout {
+                    contact: e.contact.into_fed()?,
+                    defender: e.defender.into_fed()?,
+                    flavor: e.flavor.into_fed()?,
+                }
+            }
+            Kind::GroundOut(e) => {
+                fed_schema::Event::GroundOut {
+                    flavor: e.flavor().into_fed()?,
+                    contact: e.contact.into_fed()?,
+                    fielding: e.fielding.into_fed()?,
+                    advancements: e.advancements.into_fed()?,
+                }
+            }
+            Kind::Strikeout(e) => {
+                fed_schema::Event::Strikeout { flavor: e.flavor().into_fed()?, batter: e.batter.into_fed()? }
+            }
+            Kind::Foul(e) => {
+                fed_schema::Event::Foul { flavor: e.flavor().into_fed()? }
+            }
+            Kind::HomeRun(e) => {
+                fed_schema::Event::HomeRun { contact: e.contact.into_fed()?, scores: e.scores.into_fed()? }
+            }
+            Kind::Hit(e) => {
+                fed_schema::Event::Hit {
+                    hit_type: e.hit_type().into_fed()?,
+                    flavor: e.flavor().into_fed()?,
+                    contact: e.contact.into_fed()?,
+                    fielding: e.fielding.into_fed()?,
+                    advancements: e.advancements.into_fed()?,
+                    scores: e.scores.into_fed()?,
+                }
+            }
+            Kind::Walk(e) => {
+                fed_schema::Event::Walk { flavor: e.flavor().into_fed()?, batter: e.batter.into_fed()? }
+            }
+            Kind::EndOfHalfInning(e) => {
+                fed_schema::Event::EndOfHalfInning { top_of_inning: e.top_of_inning, inning: e.inning }
+            }
+            Kind::FieldersChoice(e) => {
+                fed_schema::Event::FieldersChoice {
+                    contact: e.contact.into_fed()?,
+                    fielding: e.fielding.into_fed()?,
+                    runner_out: e.runner_out.into_fed()?,
+                }
+            }
+        })
+    }
+}
+
+impl From<&chron_schema::PlayerDesc> for PlayerDesc {
+    fn from(player: &chron_schema::PlayerDesc) -> Self {
+        PlayerDesc { id: player.id.as_bytes().to_vec(), name: player.name.clone() }
+    }
+}
+
+impl IntoFed<chron_schema::PlayerDesc> for PlayerDesc {
+    fn into_fed(self) -> anyhow::Result<chron_schema::PlayerDesc> {
+        Ok(chron_schema::PlayerDesc { id: uuid(&self.id)?, name: self.name, extra: Default::default() })
+    }
+}
+
+impl From<&chron_schema::RunnerDesc> for RunnerDesc {
+    fn from(runner: &chron_schema::RunnerDesc) -> Self {
+        RunnerDesc { id: runner.id.as_bytes().to_vec(), name: runner.name.clone(), base: runner.base }
+    }
+}
+
+impl IntoFed<chron_schema::RunnerDesc> for RunnerDesc {
+    fn into_fed(self) -> anyhow::Result<chron_schema::RunnerDesc> {
+        Ok(chron_schema::RunnerDesc { id: uuid(&self.id)?, name: self.name, base: self.base, extra: Default::default() })
+    }
+}
+
+impl From<&fed_schema::Contact> for Contact {
+    fn from(contact: &fed_schema::Contact) -> Self {
+        Contact {
+            batter: Some((&contact.batter).into()),
+            location: contact.location.map(|location| FieldLocation::from(location).into()),
+            flavor: Some((&contact.flavor).into()),
+        }
+    }
+}
+
+impl IntoFed<fed_schema::Contact> for Contact {
+    fn into_fed(self) -> anyhow::Result<fed_schema::Contact> {
+        Ok(fed_schema::Contact {
+            location: match self.location {
+                Some(_) => { Some(self.location().into_fed()?) }
+                None => { None }
+            },
+            batter: self.batter.into_fed()?,
+            flavor: self.flavor.into_fed()?,
+        })
+    }
+}
+
+impl From<&fed_schema::ContactFlavor> for ContactFlavor {
+    fn from(flavor: &fed_schema::ContactFlavor) -> Self {
+        use contact_flavor::*;
+
+        let kind = match *flavor {
+            fed_schema::ContactFlavor::NamedWithSound { sound_effect, verb } => {
+                Kind::NamedWithSound(NamedWithSound {
+                    sound_effect: SoundEffect::from(sound_effect).into(),
+                    verb: ContactVerb::from(verb).into(),
+                })
+            }
+            fed_schema::ContactFlavor::Named { verb, pitch_descriptor } => {
+                Kind::Named(Named {
+                    verb: ContactVerb::from(verb).into(),
+                    pitch_descriptor: PitchDescriptor::from(pitch_descriptor).into(),
+                })
+            }
+            fed_schema::ContactFlavor::Adjective { adjective } => {
+                Kind::Adjective(Adjective { adjective: ContactAdjective::from(adjective).into() })
+            }
+        };
+
+        ContactFlavor { kind: Some(kind) }
+    }
+}
+
+impl IntoFed<fed_schema::ContactFlavor> for ContactFlavor {
+    fn into_fed(self) -> anyhow::Result<fed_schema::ContactFlavor> {
+        use contact_flavor::*;
+
+        Ok(match self.kind.ok_or_else(|| anyhow!("ContactFlavor has no kind"))? {
+            Kind::NamedWithSound(flavor) => {
+                fed_schema::ContactFlavor::NamedWithSound {
+                    sound_effect: flavor.sound_effect().into_fed()?,
+                    verb: flavor.verb().into_fed()?,
+                }
+            }
+            Kind::Named(flavor) => {
+                fed_schema::ContactFlavor::Named {
+                    verb: flavor.verb().into_fed()?,
+                    pitch_descriptor: flavor.pitch_descriptor().into_fed()?,
+                }
+            }
+            Kind::Adjective(flavor) => {
+                fed_schema::ContactFlavor::Adjective { adjective: flavor.adjective().into_fed()? }
+            }
+        })
+    }
+}
+
+impl From<fed_schema::BallFlavor> for BallFlavor {
+    fn from(flavor: fed_schema::BallFlavor) -> Self {
+        use ball_flavor::Kind;
+
+        let kind = match flavor {
+            fed_schema::BallFlavor::DoesntBlink => { Kind::Simple(SimpleBallFlavor::DoesntBlink.into()) }
+            fed_schema::BallFlavor::JustMisses => { Kind::Simple(SimpleBallFlavor::JustMisses.into()) }
+            fed_schema::BallFlavor::LaysOffOutside => { Kind::Simple(SimpleBallFlavor::LaysOffOutside.into()) }
+            fed_schema::BallFlavor::LooksAtBallOutside => { Kind::Simple(SimpleBallFlavor::LooksAtBallOutside.into()) }
+            fed_schema::BallFlavor::MissesBigTime => { Kind::Simple(SimpleBallFlavor::MissesBigTime.into()) }
+            fed_schema::BallFlavor::Stumbles => { Kind::Simple(SimpleBallFlavor::Stumbles.into()) }
+            fed_schema::BallFlavor::ThrowsOutside => { Kind::Simple(SimpleBallFlavor::ThrowsOutside.into()) }
+            fed_schema::BallFlavor::Adjective(adjective) => { Kind::Adjective(PitchAdjective::from(adjective).into()) }
+            fed_schema::BallFlavor::BallComma => { Kind::Simple(SimpleBallFlavor::BallComma.into()) }
+            fed_schema::BallFlavor::ExtremelyOutside => { Kind::Simple(SimpleBallFlavor::ExtremelyOutside.into()) }
+            fed_schema::BallFlavor::JustOutside => { Kind::Simple(SimpleBallFlavor::JustOutside.into()) }
+            fed_schema::BallFlavor::WayOutside => { Kind::Simple(SimpleBallFlavor::WayOutside.into()) }
+            fed_schema::BallFlavor::BallPeriod => { Kind::Simple(SimpleBallFlavor::BallPeriod.into()) }
+            fed_schema::BallFlavor::MissesTheZone => { Kind::Simple(SimpleBallFlavor::MissesTheZone.into()) }
+            fed_schema::BallFlavor::DoesNotChase => { Kind::Simple(SimpleBallFlavor::DoesNotChase.into()) }
+        };
+
+        BallFlavor { kind: Some(kind) }
+    }
+}
+
+impl IntoFed<fed_schema::BallFlavor> for BallFlavor {
+    fn into_fed(self) -> anyhow::Result<fed_schema::BallFlavor> {
+        use ball_flavor::Kind;
+
+        Ok(match self.kind.ok_or_else(|| anyhow!("BallFlavor has no kind"))? {
+            Kind::Simple(simple) => {
+                match SimpleBallFlavor::try_from(simple).unwrap_or_default() {
+                    SimpleBallFlavor::Unspecified => { return Err(anyhow!("Unspecified or unknown SimpleBallFlavor")) }
+                    SimpleBallFlavor::DoesntBlink => { fed_schema::BallFlavor::DoesntBlink }
+                    SimpleBallFlavor::JustMisses => { fed_schema::BallFlavor::JustMisses }
+                    SimpleBallFlavor::LaysOffOutside => { fed_schema::BallFlavor::LaysOffOutside }
+                    SimpleBallFlavor::LooksAtBallOutside => { fed_schema::BallFlavor::LooksAtBallOutside }
+                    SimpleBallFlavor::MissesBigTime => { fed_schema::BallFlavor::MissesBigTime }
+                    SimpleBallFlavor::Stumbles => { fed_schema::BallFlavor::Stumbles }
+                    SimpleBallFlavor::ThrowsOutside => { fed_schema::BallFlavor::ThrowsOutside }
+                    SimpleBallFlavor::BallComma => { fed_schema::BallFlavor::BallComma }
+                    SimpleBallFlavor::ExtremelyOutside => { fed_schema::BallFlavor::ExtremelyOutside }
+                    SimpleBallFlavor::JustOutside => { fed_schema::BallFlavor::JustOutside }
+                    SimpleBallFlavor::WayOutside => { fed_schema::BallFlavor::WayOutside }
+                    SimpleBallFlavor::BallPeriod => { fed_schema::BallFlavor::BallPeriod }
+                    SimpleBallFlavor::MissesTheZone => { fed_schema::BallFlavor::MissesTheZone }
+                    SimpleBallFlavor::DoesNotChase => { fed_schema::BallFlavor::DoesNotChase }
+                }
+            }
+            Kind::Adjective(adjective) => {
+                fed_schema::BallFlavor::Adjective(PitchAdjective::try_from(adjective).unwrap_or_default().into_fed()?)
+            }
+        })
+    }
+}
+
+impl From<fed_schema::StrikeFlavor> for StrikeFlavor {
+    fn from(flavor: fed_schema::StrikeFlavor) -> Self {
+        use strike_flavor::Kind;
+
+        let kind = match flavor {
+            fed_schema::StrikeFlavor::None => { Kind::Simple(SimpleStrikeFlavor::None.into()) }
+            fed_schema::StrikeFlavor::Looking => { Kind::Simple(SimpleStrikeFlavor::Looking.into()) }
+            fed_schema::StrikeFlavor::Swinging => { Kind::Simple(SimpleStrikeFlavor::Swinging.into()) }
+            fed_schema::StrikeFlavor::ThrowsAStrike => { Kind::Simple(SimpleStrikeFlavor::ThrowsAStrike.into()) }
+            fed_schema::StrikeFlavor::CaughtLooking => { Kind::Simple(SimpleStrikeFlavor::CaughtLooking.into()) }
+            fed_schema::StrikeFlavor::Chases => { Kind::Simple(SimpleStrikeFlavor::Chases.into()) }
+            fed_schema::StrikeFlavor::GuessesWrong => { Kind::Simple(SimpleStrikeFlavor::GuessesWrong.into()) }
+            fed_schema::StrikeFlavor::AdjectiveSwing(adjective) => { Kind::AdjectiveSwing(SwingAdjective::from(adjective).into()) }
+            fed_schema::StrikeFlavor::DropsItIn => { Kind::Simple(SimpleStrikeFlavor::DropsItIn.into()) }
+            fed_schema::StrikeFlavor::SwingsWeakly => { Kind::Simple(SimpleStrikeFlavor::SwingsWeakly.into()) }
+        };
+
+        StrikeFlavor { kind: Some(kind) }
+    }
+}
+
+impl IntoFed<fed_schema::StrikeFlavor> for StrikeFlavor {
+    fn into_fed(self) -> anyhow::Result<fed_schema::StrikeFlavor> {
+        use strike_flavor::Kind;
+
+        Ok(match self.kind.ok_or_else(|| anyhow!("StrikeFlavor has no kind"))? {
+            Kind::Simple(simple) => {
+                match SimpleStrikeFlavor::try_from(simple).unwrap_or_default() {
+                    SimpleStrikeFlavor::Unspecified => { return Err(anyhow!("Unspecified or unknown SimpleStrikeFlavor")) }
+                    SimpleStrikeFlavor::None => { fed_schema::StrikeFlavor::None }
+                    SimpleStrikeFlavor::Looking => { fed_schema::StrikeFlavor::Looking }
+                    SimpleStrikeFlavor::Swinging => { fed_schema::StrikeFlavor::Swinging }
+                    SimpleStrikeFlavor::ThrowsAStrike => { fed_schema::StrikeFlavor::ThrowsAStrike }
+                    SimpleStrikeFlavor::CaughtLooking => { fed_schema::StrikeFlavor::CaughtLooking }
+                    SimpleStrikeFlavor::Chases => { fed_schema::StrikeFlavor::Chases }
+                    SimpleStrikeFlavor::GuessesWrong => { fed_schema::StrikeFlavor::GuessesWrong }
+                    SimpleStrikeFlavor::DropsItIn => { fed_schema::StrikeFlavor::DropsItIn }
+                    SimpleStrikeFlavor::SwingsWeakly => { fed_schema::StrikeFlavor::SwingsWeakly }
+                }
+            }
+            Kind::AdjectiveSwing(adjective) => {
+                fed_schema::StrikeFlavor::AdjectiveSwing(SwingAdjective::try_from(adjective).unwrap_or_default().into_fed()?)
+            }
+        })
+    }
+}
+
+impl From<fed_schema::FlyoutFlavor> for FlyoutFlavor {
+    fn from(flavor: fed_schema::FlyoutFlavor) -> Self {
+        use flyout_flavor::Kind;
+
+        let kind = match flavor {
+            fed_schema::FlyoutFlavor::FlyOutTo => { Kind::Simple(SimpleFlyoutFlavor::FlyOutTo.into()) }
+            fed_schema::FlyoutFlavor::IsRightThere => { Kind::Simple(SimpleFlyoutFlavor::IsRightThere.into()) }
+            fed_schema::FlyoutFlavor::MakesCatch => { Kind::Simple(SimpleFlyoutFlavor::MakesCatch.into()) }
+            fed_schema::FlyoutFlavor::MakesCatchWithAdjective(adjective) => {
+                Kind::MakesCatchWithAdjective(CatchAdjective::from(adjective).into())
+            }
+        };
+
+        FlyoutFlavor { kind: Some(kind) }
+    }
+}
+
+impl IntoFed<fed_schema::FlyoutFlavor> for FlyoutFlavor {
+    fn into_fed(self) -> anyhow::Result<fed_schema::FlyoutFlavor> {
+        use flyout_flavor::Kind;
+
+        Ok(match self.kind.ok_or_else(|| anyhow!("FlyoutFlavor has no kind"))? {
+            Kind::Simple(simple) => {
+                match SimpleFlyoutFlavor::try_from(simple).unwrap_or_default() {
+                    SimpleFlyoutFlavor::Unspecified => { return Err(anyhow!("Unspecified or unknown SimpleFlyoutFlavor")) }
+                    SimpleFlyoutFlavor::FlyOutTo => { fed_schema::FlyoutFlavor::FlyOutTo }
+                    SimpleFlyoutFlavor::IsRightThere => { fed_schema::FlyoutFlavor::IsRightThere }
+                    SimpleFlyoutFlavor::MakesCatch => { fed_schema::FlyoutFlavor::MakesCatch }
+                }
+            }
+            Kind::MakesCatchWithAdjective(adjective) => {
+                fed_schema::FlyoutFlavor::MakesCatchWithAdjective(CatchAdjective::try_from(adjective).unwrap_or_default().into_fed()?)
+            }
+        })
+    }
+}
+
+impl From<&fed_schema::Fielding> for Fielding {
+    fn from(fielding: &fed_schema::Fielding) -> Self {
+        Fielding { defender: Some((&fielding.defender).into()), flavor: FieldingFlavor::from(fielding.flavor).into() }
+    }
+}
+
+impl IntoFed<fed_schema::Fielding> for Fielding {
+    fn into_fed(self) -> anyhow::Result<fed_schema::Fielding> {
+        Ok(fed_schema::Fielding { flavor: self.flavor().into_fed()?, defender: self.defender.into_fed()? })
+    }
+}
+
+impl From<&fed_schema::FailedFielding> for FailedFielding {
+    fn from(fielding: &fed_schema::FailedFielding) -> Self {
+        FailedFielding {
+            defender: Some((&fielding.defender).into()),
+            flavor: FailedFieldingFlavor::from(fielding.flavor).into(),
+        }
+    }
+}
+
+impl IntoFed<fed_schema::FailedFielding> for FailedFielding {
+    fn into_fed(self) -> anyhow::Result<fed_schema::FailedFielding> {
+        Ok(fed_schema::FailedFielding { flavor: self.flavor().into_fed()?, defender: self.defender.into_fed()? })
+    }
+}
+
+impl From<&fed_schema::MaybeFailedFielding> for MaybeFailedFielding {
+    fn from(fielding: &fed_schema::MaybeFailedFielding) -> Self {
+        use maybe_failed_fielding::Kind;
+
+        let kind = match fielding {
+            fed_schema::MaybeFailedFielding::Fielding(fielding) => { Kind::Fielding(fielding.into()) }
+            fed_schema::MaybeFailedFielding::FailedFielding(fielding) => { Kind::FailedFielding(fielding.into()) }
+        };
+
+        MaybeFailedFielding { kind: Some(kind) }
+    }
+}
+
+impl IntoFed<fed_schema::MaybeFailedFielding> for MaybeFailedFielding {
+    fn into_fed(self) -> anyhow::Result<fed_schema::MaybeFailedFielding> {
+        use maybe_failed_fielding::Kind;
+
+        Ok(match self.kind.ok_or_else(|| anyhow!("MaybeFailedFielding has no kind"))? {
+            Kind::Fielding(fielding) => { fed_schema::MaybeFailedFielding::Fielding(fielding.into_fed()?) }
+            Kind::FailedFielding(fielding) => { fed_schema::MaybeFailedFielding::FailedFielding(fielding.into_fed()?) }
+        })
+    }
+}
+
+impl From<&fed_schema::Advancement> for Advancement {
+    fn from(advancement: &fed_schema::Advancement) -> Self {
+        Advancement { runner: Some((&advancement.runner).into()), to_base: Base::from(advancement.to_base).into() }
+    }
+}
+
+impl IntoFed<fed_schema::Advancement> for Advancement {
+    fn into_fed(self) -> anyhow::Result<fed_schema::Advancement> {
+        Ok(fed_schema::Advancement { to_base: self.to_base().into_fed()?, runner: self.runner.into_fed()? })
+    }
+}
+
+impl From<fed_schema::RunnerAdvancement> for RunnerAdvancement {
+    fn from(advancement: fed_schema::RunnerAdvancement) -> Self {
+        use runner_advancement::{Advanced, Kind};
+
+        let kind = match advancement {
+            fed_schema::RunnerAdvancement::None => { Kind::Simple(SimpleRunnerAdvancement::None.into()) }
+            fed_schema::RunnerAdvancement::Advanced(base, flavor) => {
+                Kind::Advanced(Advanced { base: Base::from(base).into(), flavor: AdvancementFlavor::from(flavor).into() })
+            }
+            fed_schema::RunnerAdvancement::Scored => { Kind::Simple(SimpleRunnerAdvancement::Scored.into()) }
+        };
+
+        RunnerAdvancement { kind: Some(kind) }
+    }
+}
+
+impl IntoFed<fed_schema::RunnerAdvancement> for RunnerAdvancement {
+    fn into_fed(self) -> anyhow::Result<fed_schema::RunnerAdvancement> {
+        use runner_advancement::Kind;
+
+        Ok(match self.kind.ok_or_else(|| anyhow!("RunnerAdvancement has no kind"))? {
+            Kind::Simple(simple) => {
+                match SimpleRunnerAdvancement::try_from(simple).unwrap_or_default() {
+                    SimpleRunnerAdvancement::Unspecified => { return Err(anyhow!("Unspecified or unknown SimpleRunnerAdvancement")) }
+                    SimpleRunnerAdvancement::None => { fed_schema::RunnerAdvancement::None }
+                    SimpleRunnerAdvancement::Scored => { fed_schema::RunnerAdvancement::Scored }
+                }
+            }
+            Kind::Advanced(advanced) => {
+                fed_schema::RunnerAdvancement::Advanced(advanced.base().into_fed()?, advanced.flavor().into_fed()?)
+            }
+        })
+    }
+}
+
+impl From<&fed_schema::RunnerAdvancementDesc> for RunnerAdvancementDesc {
+    fn from(desc: &fed_schema::RunnerAdvancementDesc) -> Self {
+        RunnerAdvancementDesc { runner: Some((&desc.runner).into()), advancement: Some(desc.advancement.into()) }
+    }
+}
+
+impl IntoFed<fed_schema::RunnerAdvancementDesc> for RunnerAdvancementDesc {
+    fn into_fed(self) -> anyhow::Result<fed_schema::RunnerAdvancementDesc> {
+        Ok(fed_schema::RunnerAdvancementDesc { runner: self.runner.into_fed()?, advancement: self.advancement.into_fed()? })
+    }
+}
+
+mirrored_enumeration!(PitchAdjective {
+    Auspicious,
+    Average,
+    Disgusting,
+    Dominant,
+    Favorable,
+    Horrible,
+    Marvelous,
+    Overpowering,
+    Potent,
+    Powerful,
+    Revolting,
+    WellLocated,
+    WellPlaced,
+});
+
+mirrored_enumeration!(SwingAdjective {
+    Pathetic,
+    Poor,
+    Sad,
+    Weak,
+});
+
+mirrored_enumeration!(SoundEffect {
+    Bam,
+    Boom,
+    Crack,
+    Smack,
+    Smash,
+    Thwack,
+    Wham,
+});
+
+mirrored_enumeration!(ContactVerb {
+    Bats,
+    Chops,
+    Clips,
+    Drags,
+    Dribbles,
+    Hits,
+    Knocks,
+    Nudges,
+    Pokes,
+    Punches,
+    Pushes,
+    Rolls,
+    Slaps,
+    Smacks,
+    Sputters,
+    Swats,
+    Taps,
+    Thumps,
+    Trickles,
+    Whacks,
+});
+
+mirrored_enumeration!(PitchDescriptor {
+    It,
+    One,
+    TheBall,
+    ThePitch,
+});
+
+mirrored_enumeration!(FieldLocation {
+    Infield,
+    LeftField,
+    DeepLeftField,
+    CenterField,
+    DeepCenterField,
+    RightField,
+    DeepRightField,
+    Wall,
+});
+
+mirrored_enumeration!(ContactAdjective {
+    Decent,
+    Depressing,
+    Hard,
+    Sad,
+    Solid,
+    Strong,
+    Weak,
+});
+
+mirrored_enumeration!(FoulFlavor {
+    FoulBall,
+    FoulTip,
+    FoulsItBack,
+    FoulsItOff,
+});
+
+mirrored_enumeration!(CatchAdjective {
+    Cool,
+    Decent,
+    Diving,
+    Fine,
+    Good,
+    Lazy,
+    Nice,
+    Poor,
+    Satisfactory,
+    Simple,
+    Solid,
+});
+
+mirrored_enumeration!(GroundoutFlavor {
+    GroundOutTo,
+    HitsAGroundout,
+    ForcedOutAtFirst,
+});
+
+mirrored_enumeration!(FieldingFlavor {
+    ChargesForIt,
+    CollectsIt,
+    CorralsIt,
+    DashesForIt,
+    DivesForIt,
+    FieldsIt,
+    GetsInFrontOfIt,
+    GetsIt,
+    GoesForIt,
+    HasABeadOnIt,
+    IsThereToCollectIt,
+    IsThereToCorralIt,
+    IsThereToFieldIt,
+    IsThereToGetIt,
+    IsThereToScoopIt,
+    IsThereToSecureIt,
+    LurchesForIt,
+    RacesForIt,
+    RacesIn,
+    RacesTowardIt,
+    ReachesForIt,
+    RunsForIt,
+    ScoopsIt,
+    SecuresIt,
+    TracksItDown,
+    TriesForIt,
+});
+
+mirrored_enumeration!(FailedFieldingFlavor {
+    BobblesIt,
+    CantCollectIt,
+    CantCorralIt,
+    CantFieldIt,
+    CantGetIt,
+    CantMakeTheCatch,
+    CantScoopIt,
+    CantSecureIt,
+    DropsIt,
+    IsLateGettingThere,
+    JustMissesTheCatch,
+    LosesIt,
+});
+
+mirrored_enumeration!(StrikeoutFlavor {
+    NamedBoth,
+    NamedBatter,
+});
+
+mirrored_enumeration!(HitType {
+    Single,
+    Double,
+    Triple,
+});
+
+mirrored_enumeration!(HitFlavor {
+    Hits,
+    IsOnWith,
+});
+
+mirrored_enumeration!(Base {
+    First,
+    Second,
+    Third,
+});
+
+mirrored_enumeration!(AdvancementFlavor {
+    To,
+    AdvancesTo,
+});
+
+mirrored_enumeration!(WalkFlavor {
+    Ball4,
+    DrawsWalk,
+    EarnsWalk,
+});
+
+/// Writes `event` to `writer` as a length-delimited `FedEvent` message
+pub fn write_event(writer: &mut impl Write, event: &fed_schema::FedEvent) -> io::Result<()> {
+    writer.write_all(&FedEvent::from(event).encode_length_delimited_to_vec())
+}
+
+/// The longest message `EventReader` will read. Events are a few hundred bytes, so anything near
+/// this is corrupt input, and allocating whatever length it claims could run out of memory.
+pub const MAX_MESSAGE_SIZE: usize = 1 << 20;
+
+/// Reads the length-delimited `FedEvent` messages written by `write_event`, converting each back
+/// to a `fed_schema::FedEvent`. Stops cleanly at the end of the input, but a message cut off part
+/// way through, or longer than `MAX_MESSAGE_SIZE`, is an error.
+pub struct EventReader<R> {
+    reader: R,
+    buffer: Vec<u8>,
+}
+
+impl<R: BufRead> EventReader<R> {
+    pub fn new(reader: R) -> Self {
+        EventReader { reader, buffer: Vec::new() }
+    }
+
+    /// Reads the length prefix, or `None` at the end of the input
+    fn read_length(&mut self) -> anyhow::Result<Option<usize>> {
+        let mut length = 0u64;
+        for i in 0..10 {
+            let mut byte = [0];
+            if self.reader.read(&mut byte)? == 0 {
+                return if i == 0 {
+                    Ok(None)
+                } else {
+                    Err(anyhow!("Input ended part way through a message length"))
+                };
+            }
+            length |= u64::from(byte[0] & 0x7f) << (7 * i);
+            if byte[0] & 0x80 == 0 {
+                return Ok(Some(length.try_into()?));
+            }
+        }
+        Err(anyhow!("Message length is too long to be a varint"))
+    }
+
+    fn read_event(&mut self) -> anyhow::Result<Option<fed_schema::FedEvent>> {
+        let Some(length) = self.read_length()? else {
+            return Ok(None);
+        };
+        if length > MAX_MESSAGE_SIZE {
+            return Err(anyhow!("Message length {length} is more than the maximum of {MAX_MESSAGE_SIZE} bytes"));
+        }
+        self.buffer.resize(length, 0);
+        self.reader.read_exact(&mut self.buffer)?;
+        Ok(Some(FedEvent::decode(self.buffer.as_slice())?.to_fed()?))
+    }
+}
+
+impl<R: BufRead> Iterator for EventReader<R> {
+    type Item = anyhow::Result<fed_schema::FedEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_event().transpose()
+    }
+}
@@ -0,0 +1,168 @@
+use uuid::Uuid;
+use crate::chron_schema::{PlayerDesc, RunnerDesc, State, TeamAtBat};
+use crate::fed_schema::{Base, Event, RunnerAdvancement};
+
+/// Advances `state` past `event` using only the information in the event itself, without any
+/// chronicler data. Starting from `State::default()` and applying every event of a game in order
+/// reconstructs the game state after each event, so `Event::lines` can be fed from Fed alone.
+///
+/// Fed doesn't record where runners go unless the event says so (e.g. an `Advancement`), so
+/// runners who aren't mentioned stay where they are unless they're forced to move.
+pub fn apply(state: &mut State, event: &Event) {
+    match event {
+        Event::PlayBall => {
+            state.started = true;
+        }
+        Event::BatterUp { batter, pitcher } => {
+            state.batter = Some(batter.clone());
+            state.pitcher = pitcher.clone();
+        }
+        Event::Ball { .. } => {
+            state.balls += 1;
+        }
+        Event::Strike { .. } => {
+            state.strikes += 1;
+        }
+        Event::Foul { .. } => {
+            // A foul can't be strike 3
+            state.strikes = (state.strikes + 1).min(2);
+        }
+        Event::Strikeout { .. } | Event::Flyout { .. } => {
+            end_plate_appearance(state);
+            state.outs += 1;
+        }
+        Event::GroundOut { advancements, .. } => {
+            end_plate_appearance(state);
+            state.outs += 1;
+            for advancement in advancements {
+                match advancement.advancement {
+                    RunnerAdvancement::None => {}
+                    RunnerAdvancement::Advanced(base, _) => {
+                        move_runner(state, &advancement.runner.id, base);
+                    }
+                    RunnerAdvancement::Scored => {
+                        remove_runner(state, &advancement.runner.id);
+                        score(state, 1.);
+                    }
+                }
+            }
+        }
+        Event::HomeRun { scores, .. } => {
+            end_plate_appearance(state);
+            // Everyone on base scores, plus the batter
+            state.baserunners.clear();
+            score(state, scores.len() as f64 + 1.);
+        }
+        Event::Hit { contact, hit_type, advancements, scores, .. } => {
+            end_plate_appearance(state);
+            for runner in scores {
+                remove_runner(state, &runner.id);
+            }
+            score(state, scores.len() as f64);
+            for advancement in advancements {
+                move_runner(state, &advancement.runner.id, advancement.to_base);
+            }
+            place_runner(state, &contact.batter, hit_type.base());
+        }
+        Event::Walk { batter, .. } => {
+            end_plate_appearance(state);
+            place_runner(state, batter, Base::First);
+        }
+        Event::EndOfHalfInning { .. } => {
+            end_plate_appearance(state);
+            state.outs = 0;
+            state.baserunners.clear();
+            if state.top_of_inning {
+                state.top_of_inning = false;
+                state.team_at_bat = TeamAtBat::Home;
+            } else {
+                state.top_of_inning = true;
+                state.inning += 1;
+                state.team_at_bat = TeamAtBat::Away;
+            }
+        }
+        Event::FieldersChoice { contact, runner_out, .. } => {
+            end_plate_appearance(state);
+            state.outs += 1;
+            remove_runner(state, &runner_out.id);
+            place_runner(state, &contact.batter, Base::First);
+        }
+    }
+}
+
+/// How many innings a game lasts, unless it's tied and goes to extra innings
+const REGULATION_INNINGS: i64 = 9;
+
+/// Whether the game was over as soon as `event` happened, given the state after it. That's when a
+/// half-inning of the last inning (or an extra inning) ends with the team that can't bat again
+/// behind, or the home team takes the lead while batting in one.
+pub fn ends_game(event: &Event, after: &State) -> bool {
+    // Innings count from 0
+    let last_inning = REGULATION_INNINGS - 1;
+    match event {
+        Event::EndOfHalfInning { top_of_inning: true, inning } => {
+            *inning >= last_inning && after.home_score > after.away_score
+        }
+        Event::EndOfHalfInning { top_of_inning: false, inning } => {
+            *inning >= last_inning && after.home_score != after.away_score
+        }
+        _ => {
+            !after.top_of_inning && after.inning >= last_inning && after.home_score > after.away_score
+        }
+    }
+}
+
+fn end_plate_appearance(state: &mut State) {
+    // Chronicler clears the batter as soon as the plate appearance is over
+    state.batter = None;
+    state.balls = 0;
+    state.strikes = 0;
+}
+
+fn score(state: &mut State, runs: f64) {
+    if state.top_of_inning {
+        state.away_score += runs;
+    } else {
+        state.home_score += runs;
+    }
+}
+
+fn remove_runner(state: &mut State, id: &Uuid) {
+    state.baserunners.retain(|runner| &runner.id != id);
+}
+
+fn move_runner(state: &mut State, id: &Uuid, to_base: Base) {
+    if let Some(runner) = state.baserunners.iter_mut().find(|runner| &runner.id == id) {
+        runner.base = to_base.index();
+    }
+}
+
+/// Puts `player` on `base`, forcing any runners in the way to advance. The batter passes through
+/// every base up to `base`, so on a double a runner on first is forced to third. A runner forced
+/// past third scores.
+fn place_runner(state: &mut State, player: &PlayerDesc, base: Base) {
+    for passing in Base::First.index()..=base.index() {
+        // Only the unbroken chain of runners starting at the base being passed is forced
+        let mut first_empty_base = passing;
+        while state.baserunners.iter().any(|runner| runner.base == first_empty_base) {
+            first_empty_base += 1;
+        }
+        for runner in &mut state.baserunners {
+            if runner.base >= passing && runner.base < first_empty_base {
+                runner.base += 1;
+            }
+        }
+    }
+
+    let before = state.baserunners.len();
+    state.baserunners.retain(|runner| runner.base <= Base::Third.index());
+    let forced_home = before - state.baserunners.len();
+    score(state, forced_home as f64);
+
+    state.baserunners.push(RunnerDesc {
+        id: player.id,
+        name: player.name.clone(),
+        base: base.index(),
+        extra: Default::default(),
+    });
+}
@@ -0,0 +1,101 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+use crate::chron_schema::GameResponse;
+
+/// What a `ReorderBuffer` hands back, in `display_order` sequence
+#[derive(Debug)]
+pub enum Released {
+    Update(Box<GameResponse>),
+    /// These display orders never arrived, and the buffer stopped waiting for them
+    Gap(Range<i64>),
+}
+
+/// Puts one game's updates back in `display_order` sequence before they go to `Parser`.
+///
+/// Chronicler can serve the same update more than once, and a game's updates can be spread
+/// across pages and timestamps in any order. The buffer drops repeats and holds updates that
+/// arrive early until the ones before them turn up. If an update still hasn't turned up once the
+/// game's chronicler timestamps have moved `window` past the earliest update waiting on it, the
+/// buffer gives up on it and reports a gap. The first update the buffer sees is taken as where the
+/// game starts, so a run that joins a game partway through doesn't wait on everything before it.
+///
+/// Serializing the buffer saves what it's holding but not its window, which has to be given again
+/// with `with_window` after deserializing.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ReorderBuffer {
+    #[serde(skip, default = "Duration::zero")]
+    window: Duration,
+    /// `None` until the first update arrives
+    next_order: Option<i64>,
+    pending: BTreeMap<i64, GameResponse>,
+    latest_timestamp: Option<DateTime<Utc>>,
+}
+
+impl ReorderBuffer {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            next_order: None,
+            pending: BTreeMap::new(),
+            latest_timestamp: None,
+        }
+    }
+
+    pub fn with_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Adds an update, returning everything that's now ready in order
+    pub fn push(&mut self, item: GameResponse) -> Vec<Released> {
+        self.latest_timestamp = self.latest_timestamp.max(Some(item.timestamp));
+
+        let display_order = item.data.display_order;
+        let next_order = *self.next_order.get_or_insert(display_order);
+        if display_order < next_order {
+            debug!(display_order, "Dropping an update that was already released");
+        } else if let Some(existing) = self.pending.get(&display_order) {
+            if existing.data == item.data {
+                debug!(display_order, "Dropping a duplicate update");
+            } else {
+                warn!(display_order, kept = ?existing.data, dropped = ?item.data,
+                    "Two different updates have the same display order. Keeping the first.");
+            }
+        } else {
+            self.pending.insert(display_order, item);
+        }
+
+        self.release(false)
+    }
+
+    /// Releases everything left, reporting any gaps. Used once no more updates are coming.
+    pub fn flush(&mut self) -> Vec<Released> {
+        self.release(true)
+    }
+
+    fn release(&mut self, flush: bool) -> Vec<Released> {
+        let mut released = Vec::new();
+        let Some(next_order) = &mut self.next_order else {
+            return released;
+        };
+        while let Some(entry) = self.pending.first_entry() {
+            let display_order = *entry.key();
+            if display_order > *next_order {
+                let waited_long_enough = self.latest_timestamp
+                    .is_some_and(|latest| latest - entry.get().timestamp >= self.window);
+                if !flush && !waited_long_enough {
+                    break;
+                }
+                released.push(Released::Gap(*next_order..display_order));
+            }
+
+            released.push(Released::Update(Box::new(entry.remove())));
+            *next_order = display_order + 1;
+        }
+
+        released
+    }
+}
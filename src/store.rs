@@ -0,0 +1,247 @@
+//! A local SQLite database of Fed events, with the games and players they involve.
+//!
+//! Events are stored as their Fed JSON alongside indexed columns for the game, sequence and event
+//! type, and `event_players` records which players each event involves and how. Event ids are
+//! derived from the game and display order, so ingesting the same events again replaces them
+//! rather than duplicating them.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+use chrono::SecondsFormat;
+use rusqlite::{params, Connection, OptionalExtension};
+use uuid::Uuid;
+use crate::fed_schema::{Event, FedEvent, MaybeFailedFielding};
+use crate::tables::event_type;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS games (
+        id TEXT PRIMARY KEY,
+        first_timestamp TEXT NOT NULL,
+        last_timestamp TEXT NOT NULL,
+        events INTEGER NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS players (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS events (
+        id TEXT PRIMARY KEY,
+        game_id TEXT NOT NULL,
+        sequence INTEGER NOT NULL,
+        type TEXT NOT NULL,
+        timestamp TEXT NOT NULL,
+        schema_version INTEGER NOT NULL,
+        json TEXT NOT NULL
+    );
+    -- Also the index for looking events up by game
+    CREATE UNIQUE INDEX IF NOT EXISTS events_game_sequence ON events (game_id, sequence);
+    CREATE INDEX IF NOT EXISTS events_type ON events (type);
+
+    CREATE TABLE IF NOT EXISTS event_players (
+        event_id TEXT NOT NULL,
+        player_id TEXT NOT NULL,
+        -- batter, pitcher, fielder or runner
+        role TEXT NOT NULL,
+        PRIMARY KEY (event_id, player_id, role)
+    );
+    CREATE INDEX IF NOT EXISTS event_players_player ON event_players (player_id, role);
+";
+
+/// The event types that end a plate appearance
+const PLATE_APPEARANCE_TYPES: &str = "'strikeout', 'flyout', 'ground_out', 'home_run', 'hit', 'walk', 'fielders_choice'";
+
+/// Every player `event` involves, as (id, name, role)
+fn players(event: &Event) -> Vec<(Uuid, &str, &'static str)> {
+    let mut players = Vec::new();
+    match event {
+        Event::PlayBall | Event::Ball { .. } | Event::Strike { .. } | Event::Foul { .. } |
+        Event::EndOfHalfInning { .. } => {}
+        Event::BatterUp { batter, pitcher } => {
+            players.push((batter.id, batter.name.as_str(), "batter"));
+            if let Some(pitcher) = pitcher {
+                players.push((pitcher.id, pitcher.name.as_str(), "pitcher"));
+            }
+        }
+        Event::Strikeout { batter, .. } | Event::Walk { batter, .. } => {
+            players.push((batter.id, batter.name.as_str(), "batter"));
+        }
+        Event::Flyout { contact, defender, .. } => {
+            players.push((contact.batter.id, contact.batter.name.as_str(), "batter"));
+            players.push((defender.id, defender.name.as_str(), "fielder"));
+        }
+        Event::GroundOut { contact, fielding, advancements, .. } => {
+            players.push((contact.batter.id, contact.batter.name.as_str(), "batter"));
+            players.push((fielding.defender.id, fielding.defender.name.as_str(), "fielder"));
+            for advancement in advancements {
+                players.push((advancement.runner.id, advancement.runner.name.as_str(), "runner"));
+            }
+        }
+        Event::HomeRun { contact, scores } => {
+            players.push((contact.batter.id, contact.batter.name.as_str(), "batter"));
+            for runner in scores {
+                players.push((runner.id, runner.name.as_str(), "runner"));
+            }
+        }
+        Event::Hit { contact, fielding, advancements, scores, .. } => {
+            players.push((contact.batter.id, contact.batter.name.as_str(), "batter"));
+            let defender = match fielding {
+                MaybeFailedFielding::Fielding(fielding) => { &fielding.defender }
+                MaybeFailedFielding::FailedFielding(fielding) => { &fielding.defender }
+            };
+            players.push((defender.id, defender.name.as_str(), "fielder"));
+            for runner in advancements.iter().map(|advancement| &advancement.runner).chain(scores) {
+                players.push((runner.id, runner.name.as_str(), "runner"));
+            }
+        }
+        Event::FieldersChoice { contact, fielding, runner_out } => {
+            players.push((contact.batter.id, contact.batter.name.as_str(), "batter"));
+            players.push((fielding.defender.id, fielding.defender.name.as_str(), "fielder"));
+            players.push((runner_out.id, runner_out.name.as_str(), "runner"));
+        }
+    }
+    players
+}
+
+pub struct Store {
+    connection: Connection,
+}
+
+impl Store {
+    /// Opens the database at `path`, creating it and its tables if need be
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        Self::new(Connection::open(path)?)
+    }
+
+    /// A database that only lives as long as the `Store`
+    pub fn in_memory() -> anyhow::Result<Self> {
+        Self::new(Connection::open_in_memory()?)
+    }
+
+    fn new(connection: Connection) -> anyhow::Result<Self> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(Store { connection })
+    }
+
+    /// Stores `events`, which can be from any number of games. Whatever's already stored for those
+    /// games is replaced, so each game's events need to be complete. Ingesting the same game again
+    /// changes nothing, and ingesting it after parsing it again leaves nothing from the old parse.
+    pub fn ingest(&mut self, events: &[FedEvent]) -> anyhow::Result<()> {
+        let game_ids = events.iter().map(|event| event.game_id.to_string()).collect::<BTreeSet<_>>();
+        let transaction = self.connection.transaction()?;
+        {
+            let mut clear_game_players = transaction.prepare_cached(
+                "DELETE FROM event_players WHERE event_id IN (SELECT id FROM events WHERE game_id = ?1)",
+            )?;
+            let mut clear_game_events = transaction.prepare_cached("DELETE FROM events WHERE game_id = ?1")?;
+            let mut upsert_event = transaction.prepare_cached(
+                "INSERT INTO events (id, game_id, sequence, type, timestamp, schema_version, json)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                 ON CONFLICT (id) DO UPDATE SET
+                     game_id = excluded.game_id, sequence = excluded.sequence, type = excluded.type,
+                     timestamp = excluded.timestamp, schema_version = excluded.schema_version,
+                     json = excluded.json",
+            )?;
+            let mut insert_event_player = transaction.prepare_cached(
+                "INSERT OR IGNORE INTO event_players (event_id, player_id, role) VALUES (?1, ?2, ?3)",
+            )?;
+            let mut upsert_player = transaction.prepare_cached(
+                "INSERT INTO players (id, name) VALUES (?1, ?2) ON CONFLICT (id) DO UPDATE SET name = excluded.name",
+            )?;
+            let mut update_game = transaction.prepare_cached(
+                "INSERT INTO games (id, first_timestamp, last_timestamp, events)
+                 SELECT game_id, MIN(timestamp), MAX(timestamp), COUNT(*) FROM events WHERE game_id = ?1 GROUP BY game_id
+                 ON CONFLICT (id) DO UPDATE SET
+                     first_timestamp = excluded.first_timestamp, last_timestamp = excluded.last_timestamp,
+                     events = excluded.events",
+            )?;
+
+            for game_id in &game_ids {
+                clear_game_players.execute(params![game_id])?;
+                clear_game_events.execute(params![game_id])?;
+            }
+
+            for event in events {
+                let event_id = event.id.to_string();
+                upsert_event.execute(params![
+                    event_id,
+                    event.game_id.to_string(),
+                    event.sequence,
+                    event_type(&event.event),
+                    // Fixed width, so timestamps sort as text
+                    event.timestamp.to_rfc3339_opts(SecondsFormat::Nanos, true),
+                    event.schema_version,
+                    serde_json::to_string(event)?,
+                ])?;
+
+                for (player_id, name, role) in players(&event.event) {
+                    let player_id = player_id.to_string();
+                    upsert_player.execute(params![player_id, name])?;
+                    insert_event_player.execute(params![event_id, player_id, role])?;
+                }
+            }
+
+            for game_id in &game_ids {
+                update_game.execute(params![game_id])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn query_events(&self, sql: &str, param: &str) -> anyhow::Result<Vec<FedEvent>> {
+        let mut statement = self.connection.prepare_cached(sql)?;
+        let rows = statement.query_map(params![param], |row| row.get::<_, String>(0))?;
+        let mut events = Vec::new();
+        for json in rows {
+            events.push(serde_json::from_str(&json?)?);
+        }
+        Ok(events)
+    }
+
+    /// Every stored event of a game, in order
+    pub fn game_events(&self, game_id: Uuid) -> anyhow::Result<Vec<FedEvent>> {
+        self.query_events(
+            "SELECT json FROM events WHERE game_id = ?1 ORDER BY sequence",
+            &game_id.to_string(),
+        )
+    }
+
+    /// Every stored event of a type (as in `Event`'s `type` tag, e.g. `home_run`), in game order
+    pub fn events_of_type(&self, event_type: &str) -> anyhow::Result<Vec<FedEvent>> {
+        self.query_events(
+            "SELECT json FROM events WHERE type = ?1 ORDER BY timestamp, game_id, sequence",
+            event_type,
+        )
+    }
+
+    /// Every stored event involving a player in any role, in game order
+    pub fn player_events(&self, player_id: Uuid) -> anyhow::Result<Vec<FedEvent>> {
+        self.query_events(
+            "SELECT json FROM events WHERE id IN (SELECT event_id FROM event_players WHERE player_id = ?1)
+             ORDER BY timestamp, game_id, sequence",
+            &player_id.to_string(),
+        )
+    }
+
+    /// The event that ended each of a batter's plate appearances, in game order
+    pub fn plate_appearances(&self, batter_id: Uuid) -> anyhow::Result<Vec<FedEvent>> {
+        self.query_events(
+            &format!(
+                "SELECT json FROM events
+                 WHERE id IN (SELECT event_id FROM event_players WHERE player_id = ?1 AND role = 'batter')
+                     AND type IN ({PLATE_APPEARANCE_TYPES})
+                 ORDER BY timestamp, game_id, sequence"
+            ),
+            &batter_id.to_string(),
+        )
+    }
+
+    /// The name a player was last stored with
+    pub fn player_name(&self, player_id: Uuid) -> anyhow::Result<Option<String>> {
+        Ok(self.connection
+            .query_row("SELECT name FROM players WHERE id = ?1", params![player_id.to_string()], |row| row.get(0))
+            .optional()?)
+    }
+}
@@ -0,0 +1,122 @@
+use chrono::{DateTime, Duration, Utc};
+use crate::chron_schema::{ExtraFields, GameUpdateDelta, PlayerDesc, State, StateDelta};
+use crate::fed_schema::Event;
+use crate::reducer::apply;
+
+/// How many seconds apart synthesized updates are
+const DISPLAY_DELAY: i64 = 5;
+
+/// The players fielding for each team. Fed events only mention the defenders who touched the
+/// ball, but `Parser` needs the whole list to recognize them.
+#[derive(Debug, Clone)]
+pub struct Rosters {
+    pub home_defenders: Vec<PlayerDesc>,
+    pub away_defenders: Vec<PlayerDesc>,
+}
+
+/// The inverse of `Parser`: turns Fed events back into the update stream chronicler would have
+/// served for them.
+pub struct Synthesizer {
+    rosters: Rosters,
+    state: State,
+    display_order: i64,
+    display_time: DateTime<Utc>,
+}
+
+impl Synthesizer {
+    pub fn new(rosters: Rosters, start_time: DateTime<Utc>) -> Self {
+        Self {
+            rosters,
+            state: State::default(),
+            display_order: 0,
+            display_time: start_time,
+        }
+    }
+
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    pub fn synthesize(&mut self, event: &Event) -> anyhow::Result<Vec<GameUpdateDelta>> {
+        let mut after = self.state.clone();
+        apply(&mut after, event);
+        after.defenders = Some(if after.top_of_inning {
+            self.rosters.home_defenders.clone()
+        } else {
+            self.rosters.away_defenders.clone()
+        });
+
+        let lines = event.lines(&after)?;
+
+        // `Parser` tells events apart by which update the state changes in, so the state has to
+        // change on the same line chronicler would have changed it on. For contact, the contact
+        // line clears the batter and the count, then the result line has everything else.
+        let result_line = match event {
+            Event::Flyout { .. } | Event::HomeRun { .. } => { 1 }
+            Event::GroundOut { .. } | Event::Hit { .. } | Event::FieldersChoice { .. } => { 2 }
+            _ => { 0 }
+        };
+        let contact_state = State {
+            batter: None,
+            balls: 0,
+            strikes: 0,
+            ..self.state.clone()
+        };
+        let line_states = (0..lines.len())
+            .map(|i| if i < result_line { contact_state.clone() } else { after.clone() })
+            .collect::<Vec<_>>();
+
+        let mut prev_state = &self.state;
+        let mut updates = Vec::with_capacity(lines.len());
+        for (display_text, line_state) in lines.into_iter().zip(&line_states) {
+            updates.push(GameUpdateDelta {
+                changed_state: diff(prev_state, line_state),
+                display_delay: DISPLAY_DELAY,
+                display_order: self.display_order,
+                display_text,
+                display_time: self.display_time,
+                extra: ExtraFields::new(),
+            });
+            self.display_order += 1;
+            self.display_time += Duration::seconds(DISPLAY_DELAY);
+            prev_state = line_state;
+        }
+
+        self.state = after;
+        Ok(updates)
+    }
+}
+
+/// Synthesizes the whole update stream for a game from its events
+pub fn synthesize_game(events: &[Event], rosters: Rosters, start_time: DateTime<Utc>) -> anyhow::Result<Vec<GameUpdateDelta>> {
+    let mut synthesizer = Synthesizer::new(rosters, start_time);
+    let mut updates = Vec::new();
+    for event in events {
+        updates.extend(synthesizer.synthesize(event)?);
+    }
+
+    Ok(updates)
+}
+
+fn diff(from: &State, to: &State) -> StateDelta {
+    fn changed<T: PartialEq + Clone>(from: &T, to: &T) -> Option<T> {
+        if from == to { None } else { Some(to.clone()) }
+    }
+
+    StateDelta {
+        batter: changed(&from.batter, &to.batter),
+        defenders: changed(&from.defenders, &to.defenders),
+        pitcher: changed(&from.pitcher, &to.pitcher),
+        baserunners: changed(&from.baserunners, &to.baserunners),
+        started: changed(&from.started, &to.started),
+        team_at_bat: changed(&from.team_at_bat, &to.team_at_bat),
+        inning: changed(&from.inning, &to.inning),
+        top_of_inning: changed(&from.top_of_inning, &to.top_of_inning),
+        balls: changed(&from.balls, &to.balls),
+        strikes: changed(&from.strikes, &to.strikes),
+        outs: changed(&from.outs, &to.outs),
+        home_score: changed(&from.home_score, &to.home_score),
+        away_score: changed(&from.away_score, &to.away_score),
+        extra: ExtraFields::new(),
+    }
+}
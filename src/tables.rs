@@ -0,0 +1,306 @@
+//! Flattens Fed events into rows for analysis: one table of pitches, one of plate appearances and
+//! one of baserunning. Each row carries the game situation it happened in (inning, count, who was
+//! batting and pitching), which comes from replaying the game's events with the reducer, so a
+//! game's events have to be flattened together and from the start.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use anyhow::anyhow;
+use itertools::Itertools;
+use serde::Serialize;
+use uuid::Uuid;
+use crate::chron_schema::{PlayerDesc, State};
+use crate::fed_schema::{Event, FedEvent, MaybeFailedFielding};
+use crate::migrations;
+use crate::reducer::apply;
+
+/// Every pitch, including the ones that end a plate appearance
+#[derive(Debug, Clone, Serialize)]
+pub struct PitchRow {
+    pub game_id: Uuid,
+    pub event_id: Uuid,
+    pub sequence: u64,
+    pub inning: i64,
+    pub top_of_inning: bool,
+    pub batter_id: Option<Uuid>,
+    pub batter_name: Option<String>,
+    pub pitcher_id: Option<Uuid>,
+    pub pitcher_name: Option<String>,
+    /// The count before the pitch
+    pub balls: i64,
+    pub strikes: i64,
+    pub outs: i64,
+    /// `ball`, `strike`, `foul`, `strikeout`, `walk` or `in_play`
+    pub result: &'static str,
+    /// The event's flavor, if it has one, e.g. `looks_at_ball_outside` or `adjective:well_located`
+    pub flavor: Option<String>,
+}
+
+/// Every plate appearance, as of the event that ended it
+#[derive(Debug, Clone, Serialize)]
+pub struct PlateAppearanceRow {
+    pub game_id: Uuid,
+    pub event_id: Uuid,
+    pub sequence: u64,
+    pub inning: i64,
+    pub top_of_inning: bool,
+    pub batter_id: Uuid,
+    pub batter_name: String,
+    pub pitcher_id: Option<Uuid>,
+    pub pitcher_name: Option<String>,
+    /// The count and outs before the last pitch
+    pub balls: i64,
+    pub strikes: i64,
+    pub outs: i64,
+    /// The `Event` type that ended the plate appearance, e.g. `hit` or `strikeout`
+    pub outcome: &'static str,
+    pub hit_type: Option<String>,
+    pub location: Option<String>,
+    pub fielder_id: Option<Uuid>,
+    pub fielder_name: Option<String>,
+    pub flavor: Option<String>,
+    pub runs_scored: f64,
+}
+
+/// Every time a runner (including the batter) reached, moved between, or left the bases at the end
+/// of a plate appearance
+#[derive(Debug, Clone, Serialize)]
+pub struct BaserunningRow {
+    pub game_id: Uuid,
+    pub event_id: Uuid,
+    pub sequence: u64,
+    pub inning: i64,
+    pub top_of_inning: bool,
+    pub runner_id: Uuid,
+    pub runner_name: String,
+    /// 0 for the batter
+    pub from_base: i64,
+    /// `None` if the runner scored or was put out
+    pub to_base: Option<i64>,
+    /// `reached`, `advanced`, `held`, `scored` or `out`
+    pub outcome: &'static str,
+    /// The `Event` type the movement happened on
+    pub event_type: &'static str,
+}
+
+#[derive(Debug, Default)]
+pub struct Tables {
+    pub pitches: Vec<PitchRow>,
+    pub plate_appearances: Vec<PlateAppearanceRow>,
+    pub baserunning: Vec<BaserunningRow>,
+}
+
+impl Tables {
+    /// Adds `other`'s rows after this one's, e.g. to put several games in one table
+    pub fn append(&mut self, mut other: Tables) {
+        self.pitches.append(&mut other.pitches);
+        self.plate_appearances.append(&mut other.plate_appearances);
+        self.baserunning.append(&mut other.baserunning);
+    }
+}
+
+/// Reads Fed NDJSON files (as written by the `--output` flag), upgrading older schema versions,
+/// and returns each game's events in order
+pub fn load_games(paths: &[PathBuf]) -> anyhow::Result<Vec<Vec<FedEvent>>> {
+    let mut values = Vec::new();
+    for path in paths {
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                values.push(serde_json::from_str(&line)?);
+            }
+        }
+    }
+
+    // `upgrade` returns events grouped by game, in sequence order
+    let games = migrations::upgrade(values)?.into_iter()
+        .group_by(|event| event.game_id)
+        .into_iter()
+        .map(|(_, events)| events.collect())
+        .collect();
+    Ok(games)
+}
+
+/// The `type` tag `event` is serialized with
+pub fn event_type(event: &Event) -> &'static str {
+    match event {
+        Event::PlayBall => { "play_ball" }
+        Event::BatterUp { .. } => { "batter_up" }
+        Event::Ball { .. } => { "ball" }
+        Event::Strike { .. } => { "strike" }
+        Event::Flyout { .. } => { "flyout" }
+        Event::GroundOut { .. } => { "ground_out" }
+        Event::Strikeout { .. } => { "strikeout" }
+        Event::Foul { .. } => { "foul" }
+        Event::HomeRun { .. } => { "home_run" }
+        Event::Hit { .. } => { "hit" }
+        Event::Walk { .. } => { "walk" }
+        Event::EndOfHalfInning { .. } => { "end_of_half_inning" }
+        Event::FieldersChoice { .. } => { "fielders_choice" }
+    }
+}
+
+/// A flavor (or any other Fed enum) as a single string: its serialized name, with any data after
+/// colons, e.g. `fly_out_to`, `adjective:well_located` or `named_with_sound:wham:hits`
+fn flavor_string(flavor: &impl Serialize) -> String {
+    // Enum variants with data are `{"variant": data}`, and struct variants' data is an object whose
+    // keys would only repeat what the column already says, so only the variant name is kept
+    fn push(value: &serde_json::Value, out: &mut Vec<String>) {
+        match value {
+            serde_json::Value::String(string) => { out.push(string.clone()) }
+            serde_json::Value::Array(values) => { values.iter().for_each(|value| push(value, out)) }
+            serde_json::Value::Object(map) => { map.values().for_each(|value| push(value, out)) }
+            other => { out.push(other.to_string()) }
+        }
+    }
+
+    let mut parts = Vec::new();
+    match serde_json::to_value(flavor).expect("Fed enums always serialize") {
+        serde_json::Value::Object(variant) => {
+            for (name, data) in variant {
+                parts.push(name);
+                push(&data, &mut parts);
+            }
+        }
+        other => { push(&other, &mut parts) }
+    }
+    parts.join(":")
+}
+
+fn player_columns(player: Option<&PlayerDesc>) -> (Option<Uuid>, Option<String>) {
+    (player.map(|player| player.id), player.map(|player| player.name.clone()))
+}
+
+/// Flattens one game's events, which must be the whole game so far in sequence order
+pub fn flatten(events: &[FedEvent]) -> anyhow::Result<Tables> {
+    let mut tables = Tables::default();
+    let mut state = State::default();
+    for (expected, event) in (0..).zip(events) {
+        if event.sequence != expected {
+            return Err(anyhow!("Game {} needs its events from the start, in order, but sequence {expected} is missing", event.game_id));
+        }
+        let before = state.clone();
+        apply(&mut state, &event.event);
+        push_rows(&mut tables, event, &before, &state);
+    }
+
+    Ok(tables)
+}
+
+fn push_rows(tables: &mut Tables, event: &FedEvent, before: &State, after: &State) {
+    let (result, flavor) = match &event.event {
+        Event::Ball { flavor } => { ("ball", Some(flavor_string(flavor))) }
+        Event::Strike { flavor } => { ("strike", Some(flavor_string(flavor))) }
+        Event::Foul { flavor } => { ("foul", Some(flavor_string(flavor))) }
+        Event::Strikeout { flavor, .. } => { ("strikeout", Some(flavor_string(flavor))) }
+        Event::Walk { flavor, .. } => { ("walk", Some(flavor_string(flavor))) }
+        Event::Flyout { .. } | Event::GroundOut { .. } | Event::HomeRun { .. } | Event::Hit { .. } |
+        Event::FieldersChoice { .. } => { ("in_play", None) }
+        Event::PlayBall | Event::BatterUp { .. } | Event::EndOfHalfInning { .. } => { return; }
+    };
+
+    let (batter_id, batter_name) = player_columns(before.batter.as_ref());
+    let (pitcher_id, pitcher_name) = player_columns(before.pitcher.as_ref());
+    tables.pitches.push(PitchRow {
+        game_id: event.game_id,
+        event_id: event.id,
+        sequence: event.sequence,
+        inning: before.inning,
+        top_of_inning: before.top_of_inning,
+        batter_id,
+        batter_name,
+        pitcher_id,
+        pitcher_name: pitcher_name.clone(),
+        balls: before.balls,
+        strikes: before.strikes,
+        outs: before.outs,
+        result,
+        flavor: flavor.clone(),
+    });
+
+    let (batter, hit_type, contact, fielder, flavor) = match &event.event {
+        Event::Strikeout { batter, .. } | Event::Walk { batter, .. } => { (batter, None, None, None, flavor) }
+        Event::Flyout { contact, defender, flavor } => {
+            (&contact.batter, None, Some(contact), Some(defender), Some(flavor_string(flavor)))
+        }
+        Event::GroundOut { contact, fielding, flavor, .. } => {
+            (&contact.batter, None, Some(contact), Some(&fielding.defender), Some(flavor_string(flavor)))
+        }
+        Event::HomeRun { contact, .. } => { (&contact.batter, None, Some(contact), None, None) }
+        Event::Hit { contact, fielding, hit_type, flavor, .. } => {
+            let fielder = match fielding {
+                MaybeFailedFielding::Fielding(fielding) => { &fielding.defender }
+                MaybeFailedFielding::FailedFielding(fielding) => { &fielding.defender }
+            };
+            (&contact.batter, Some(flavor_string(hit_type)), Some(contact), Some(fielder), Some(flavor_string(flavor)))
+        }
+        Event::FieldersChoice { contact, fielding, .. } => {
+            (&contact.batter, None, Some(contact), Some(&fielding.defender), None)
+        }
+        _ => { return; }
+    };
+
+    let outcome = event_type(&event.event);
+    let (fielder_id, fielder_name) = player_columns(fielder);
+    let (batting_before, batting_after) = if before.top_of_inning {
+        (before.away_score, after.away_score)
+    } else {
+        (before.home_score, after.home_score)
+    };
+    tables.plate_appearances.push(PlateAppearanceRow {
+        game_id: event.game_id,
+        event_id: event.id,
+        sequence: event.sequence,
+        inning: before.inning,
+        top_of_inning: before.top_of_inning,
+        batter_id: batter.id,
+        batter_name: batter.name.clone(),
+        pitcher_id,
+        pitcher_name,
+        balls: before.balls,
+        strikes: before.strikes,
+        outs: before.outs,
+        outcome,
+        hit_type,
+        location: contact.and_then(|contact| contact.location).map(|location| flavor_string(&location)),
+        fielder_id,
+        fielder_name,
+        flavor,
+        runs_scored: batting_after - batting_before,
+    });
+
+    // Where everyone ended up, by comparing the bases before and after
+    let mut movements = Vec::new();
+    for runner in &before.baserunners {
+        let (to_base, outcome) = match after.baserunners.iter().find(|after| after.id == runner.id) {
+            Some(after) if after.base == runner.base => { (Some(after.base), "held") }
+            Some(after) => { (Some(after.base), "advanced") }
+            None if matches!(&event.event, Event::FieldersChoice { runner_out, .. } if runner_out.id == runner.id) => {
+                (None, "out")
+            }
+            None => { (None, "scored") }
+        };
+        movements.push((runner.id, runner.name.clone(), runner.base, to_base, outcome));
+    }
+    if let Some(reached) = after.baserunners.iter().find(|runner| runner.id == batter.id) {
+        movements.push((batter.id, batter.name.clone(), 0, Some(reached.base), "reached"));
+    } else if matches!(event.event, Event::HomeRun { .. }) {
+        movements.push((batter.id, batter.name.clone(), 0, None, "scored"));
+    }
+    for (runner_id, runner_name, from_base, to_base, movement) in movements {
+        tables.baserunning.push(BaserunningRow {
+            game_id: event.game_id,
+            event_id: event.id,
+            sequence: event.sequence,
+            inning: before.inning,
+            top_of_inning: before.top_of_inning,
+            runner_id,
+            runner_name,
+            from_base,
+            to_base,
+            outcome: movement,
+            event_type: outcome,
+        });
+    }
+}
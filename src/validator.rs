@@ -0,0 +1,201 @@
+use std::fmt::{Display, Formatter};
+use uuid::Uuid;
+use crate::chron_schema::{RunnerDesc, State};
+use crate::fed_schema::{Base, Event, RunnerAdvancement};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Discrepancy {
+    pub field: &'static str,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl Display for Discrepancy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Expected {} to be {}, but it was {}", self.field, self.expected, self.actual)
+    }
+}
+
+#[derive(Default)]
+struct Discrepancies(Vec<Discrepancy>);
+
+impl Discrepancies {
+    fn check<T: PartialEq + Display>(&mut self, field: &'static str, expected: T, actual: T) {
+        if expected != actual {
+            self.0.push(Discrepancy {
+                field,
+                expected: expected.to_string(),
+                actual: actual.to_string(),
+            });
+        }
+    }
+
+    fn check_on_base(&mut self, state: &State, id: Uuid, name: &str, base: i64) {
+        let actual = state.baserunners.iter().find(|runner| runner.id == id);
+        self.check("baserunner", format!("{name} on base {base}"), match actual {
+            Some(runner) => format!("{name} on base {}", runner.base),
+            None => format!("{name} not on base"),
+        });
+    }
+
+    fn check_not_on_base(&mut self, state: &State, id: Uuid, name: &str) {
+        if let Some(runner) = state.baserunners.iter().find(|runner| runner.id == id) {
+            self.check("baserunner", format!("{name} not on base"), format!("{name} on base {}", runner.base));
+        }
+    }
+
+    fn check_runners_unchanged(&mut self, before: &State, after: &State) {
+        self.check("baserunners", runners_string(&before.baserunners), runners_string(&after.baserunners));
+    }
+
+    fn check_bases_empty(&mut self, after: &State) {
+        self.check("baserunners", runners_string(&[]), runners_string(&after.baserunners));
+    }
+}
+
+fn runners_string(runners: &[RunnerDesc]) -> String {
+    let mut runners = runners.iter()
+        .map(|runner| format!("{} on base {}", runner.name, runner.base))
+        .collect::<Vec<_>>();
+    runners.sort();
+    format!("[{}]", runners.join(", "))
+}
+
+/// Checks that the effect `event` should have on the game state (outs added, runs scored, runners
+/// moved, count reset) matches the difference between `before` and `after`, which are the states
+/// before the first and after the last update that made up the event. An empty result means the
+/// event is consistent with the state.
+pub fn validate(event: &Event, before: &State, after: &State) -> Vec<Discrepancy> {
+    let mut d = Discrepancies::default();
+
+    let (batting_before, fielding_before, batting_after, fielding_after) = if before.top_of_inning {
+        (before.away_score, before.home_score, after.away_score, after.home_score)
+    } else {
+        (before.home_score, before.away_score, after.home_score, after.away_score)
+    };
+
+    // Every event that doesn't end a half-inning happens within one, so the inning can't change
+    if !matches!(event, Event::EndOfHalfInning { .. }) {
+        d.check("inning", before.inning, after.inning);
+        d.check("top_of_inning", before.top_of_inning, after.top_of_inning);
+        d.check("fielding team's score", fielding_before, fielding_after);
+    }
+
+    match event {
+        Event::PlayBall | Event::BatterUp { .. } => {
+            d.check("outs", before.outs, after.outs);
+            d.check("batting team's score", batting_before, batting_after);
+        }
+        Event::Ball { .. } => {
+            d.check("balls", before.balls + 1, after.balls);
+            d.check("strikes", before.strikes, after.strikes);
+            d.check("outs", before.outs, after.outs);
+            d.check("batting team's score", batting_before, batting_after);
+            d.check_runners_unchanged(before, after);
+        }
+        Event::Strike { .. } => {
+            d.check("balls", before.balls, after.balls);
+            d.check("strikes", before.strikes + 1, after.strikes);
+            d.check("outs", before.outs, after.outs);
+            d.check("batting team's score", batting_before, batting_after);
+            d.check_runners_unchanged(before, after);
+        }
+        Event::Foul { .. } => {
+            // A foul can't be strike 3
+            d.check("balls", before.balls, after.balls);
+            d.check("strikes", (before.strikes + 1).min(2), after.strikes);
+            d.check("outs", before.outs, after.outs);
+            d.check("batting team's score", batting_before, batting_after);
+            d.check_runners_unchanged(before, after);
+        }
+        Event::Strikeout { batter, .. } => {
+            check_count_reset(&mut d, after);
+            d.check("outs", before.outs + 1, after.outs);
+            d.check("batting team's score", batting_before, batting_after);
+            d.check_not_on_base(after, batter.id, &batter.name);
+            d.check_runners_unchanged(before, after);
+        }
+        Event::Flyout { contact, .. } => {
+            check_count_reset(&mut d, after);
+            d.check("outs", before.outs + 1, after.outs);
+            d.check("batting team's score", batting_before, batting_after);
+            d.check_not_on_base(after, contact.batter.id, &contact.batter.name);
+        }
+        Event::GroundOut { contact, advancements, .. } => {
+            check_count_reset(&mut d, after);
+            d.check("outs", before.outs + 1, after.outs);
+            d.check_not_on_base(after, contact.batter.id, &contact.batter.name);
+
+            let mut runs = 0.;
+            for advancement in advancements {
+                let runner = &advancement.runner;
+                match advancement.advancement {
+                    RunnerAdvancement::None => {
+                        d.check_on_base(after, runner.id, &runner.name, runner.base);
+                    }
+                    RunnerAdvancement::Advanced(base, _) => {
+                        d.check_on_base(after, runner.id, &runner.name, base.index());
+                    }
+                    RunnerAdvancement::Scored => {
+                        d.check_not_on_base(after, runner.id, &runner.name);
+                        runs += 1.;
+                    }
+                }
+            }
+            d.check("batting team's score", batting_before + runs, batting_after);
+        }
+        Event::HomeRun { scores, .. } => {
+            check_count_reset(&mut d, after);
+            d.check("outs", before.outs, after.outs);
+            // Everyone on base scores, plus the batter
+            d.check("runners scoring", before.baserunners.len(), scores.len());
+            d.check("batting team's score", batting_before + before.baserunners.len() as f64 + 1., batting_after);
+            d.check_bases_empty(after);
+        }
+        Event::Hit { contact, hit_type, advancements, scores, .. } => {
+            check_count_reset(&mut d, after);
+            d.check("outs", before.outs, after.outs);
+            d.check("batting team's score", batting_before + scores.len() as f64, batting_after);
+            d.check_on_base(after, contact.batter.id, &contact.batter.name, hit_type.base().index());
+            for advancement in advancements {
+                d.check_on_base(after, advancement.runner.id, &advancement.runner.name, advancement.to_base.index());
+            }
+            for runner in scores {
+                d.check_not_on_base(after, runner.id, &runner.name);
+            }
+        }
+        Event::Walk { batter, .. } => {
+            check_count_reset(&mut d, after);
+            d.check("outs", before.outs, after.outs);
+            d.check_on_base(after, batter.id, &batter.name, Base::First.index());
+
+            // A runner only scores on a walk if the bases were loaded
+            let bases_loaded = [Base::First, Base::Second, Base::Third].iter()
+                .all(|base| before.baserunners.iter().any(|runner| runner.base == base.index()));
+            d.check("batting team's score", batting_before + if bases_loaded { 1. } else { 0. }, batting_after);
+        }
+        Event::EndOfHalfInning { top_of_inning, inning } => {
+            d.check("ending half-inning's top_of_inning", before.top_of_inning, *top_of_inning);
+            d.check("ending half-inning's inning", before.inning, *inning);
+            d.check("top_of_inning", !before.top_of_inning, after.top_of_inning);
+            d.check("inning", if before.top_of_inning { before.inning } else { before.inning + 1 }, after.inning);
+            d.check("outs", 0, after.outs);
+            d.check("home_score", before.home_score, after.home_score);
+            d.check("away_score", before.away_score, after.away_score);
+            d.check_bases_empty(after);
+        }
+        Event::FieldersChoice { contact, runner_out, .. } => {
+            check_count_reset(&mut d, after);
+            d.check("outs", before.outs + 1, after.outs);
+            d.check_on_base(after, contact.batter.id, &contact.batter.name, Base::First.index());
+            d.check_not_on_base(after, runner_out.id, &runner_out.name);
+        }
+    }
+
+    d.0
+}
+
+fn check_count_reset(d: &mut Discrepancies, after: &State) {
+    d.check("balls", 0, after.balls);
+    d.check("strikes", 0, after.strikes);
+}
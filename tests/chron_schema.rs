@@ -0,0 +1,12 @@
+use fed2::chron_schema::{State, StateDelta};
+
+#[test]
+fn update_sets_each_score() {
+    let mut state = State::default();
+    state.update(StateDelta { home_score: Some(1.), away_score: Some(2.), ..StateDelta::default() });
+    assert_eq!((state.home_score, state.away_score), (1., 2.));
+
+    // Fields the delta leaves out keep their values
+    state.update(StateDelta { away_score: Some(3.), ..StateDelta::default() });
+    assert_eq!((state.home_score, state.away_score), (1., 3.));
+}
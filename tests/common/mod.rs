@@ -0,0 +1,54 @@
+//! Fixtures shared by the integration tests. Each test file only uses some of them.
+#![allow(dead_code)]
+
+use chrono::{TimeZone, Utc};
+use fed2::chron_schema::PlayerDesc;
+use fed2::fed_schema::*;
+use uuid::Uuid;
+
+pub const GAME: Uuid = Uuid::from_u128(100);
+
+pub fn player(n: u128, name: &str) -> PlayerDesc {
+    PlayerDesc { id: Uuid::from_u128(n), name: name.to_string(), extra: Default::default() }
+}
+
+/// `event` as the `sequence`th event of `GAME`, one second after the one before
+pub fn fed_event(sequence: u64, event: Event) -> FedEvent {
+    let time = Utc.timestamp_opt(1_600_000_000 + sequence as i64, 0).unwrap();
+    FedEvent {
+        schema_version: SCHEMA_VERSION,
+        id: FedEvent::id_for(GAME, sequence as i64),
+        game_id: GAME,
+        sequence,
+        timestamp: time,
+        display_time: time,
+        first_display_order: sequence as i64,
+        last_display_order: sequence as i64,
+        event,
+    }
+}
+
+/// A walk, then a two-run home run
+pub fn game() -> Vec<FedEvent> {
+    let walker = player(1, "Walker");
+    let slugger = player(2, "Slugger");
+    let pitcher = player(3, "Pitcher");
+    let events = vec![
+        Event::PlayBall,
+        Event::BatterUp { batter: walker.clone(), pitcher: Some(pitcher.clone()) },
+        Event::Ball { flavor: BallFlavor::Adjective(PitchAdjective::WellLocated) },
+        Event::Walk { batter: walker.clone(), flavor: WalkFlavor::DrawsWalk },
+        Event::BatterUp { batter: slugger.clone(), pitcher: Some(pitcher) },
+        Event::Strike { flavor: StrikeFlavor::Looking },
+        Event::HomeRun {
+            contact: Contact {
+                batter: slugger,
+                location: Some(FieldLocation::DeepLeftField),
+                flavor: ContactFlavor::NamedWithSound { sound_effect: SoundEffect::Wham, verb: ContactVerb::Hits },
+            },
+            scores: vec![walker],
+        },
+    ];
+
+    (0..).zip(events).map(|(sequence, event)| fed_event(sequence, event)).collect()
+}
@@ -0,0 +1,103 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use chrono::{TimeZone, Utc};
+use fed2::event_source::{EventSource, FileSource};
+use serde_json::json;
+
+/// A chronicler game event, as one line of NDJSON
+fn item(display_order: i64) -> String {
+    json!({
+        "game_id": "11111111-1111-1111-1111-111111111111",
+        "timestamp": Utc.timestamp_opt(1_672_531_200 + display_order, 0).unwrap(),
+        "data": {
+            "changedState": {},
+            "displayDelay": 5,
+            "displayOrder": display_order,
+            "displayText": "",
+            "displayTime": "2023-01-01T00:00:00Z",
+        },
+    }).to_string()
+}
+
+fn ndjson(display_orders: impl IntoIterator<Item = i64>) -> String {
+    display_orders.into_iter().map(|display_order| item(display_order) + "\n").collect()
+}
+
+/// A saved chronicler game-events page
+fn page(display_orders: impl IntoIterator<Item = i64>) -> String {
+    let items: Vec<_> = display_orders.into_iter().map(item).collect();
+    format!(r#"{{"items": [{}], "next_page": null}}"#, items.join(", "))
+}
+
+fn gzip(contents: &str) -> Vec<u8> {
+    let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gzip.write_all(contents.as_bytes()).unwrap();
+    gzip.finish().unwrap()
+}
+
+/// A fresh directory for one test to write its files to
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("fed2-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write(path: &Path, contents: &[u8]) {
+    std::fs::write(path, contents).unwrap();
+}
+
+async fn display_orders(source: &mut FileSource) -> Vec<i64> {
+    let mut display_orders = Vec::new();
+    while let Some(page) = source.next_page().await.unwrap() {
+        display_orders.extend(page.iter().map(|item| item.data.display_order));
+    }
+    display_orders
+}
+
+#[tokio::test]
+async fn reads_every_format_in_name_order() {
+    let dir = temp_dir("file-source");
+    write(&dir.join("1-page.json"), page(0..2).as_bytes());
+    // Blank lines are skipped
+    write(&dir.join("2-items.ndjson"), (ndjson(2..4) + "\n").as_bytes());
+    write(&dir.join("3-items.jsonl"), ndjson(4..5).as_bytes());
+    write(&dir.join("4-items.ndjson.gz"), &gzip(&ndjson(5..7)));
+    write(&dir.join("5-page.json.zst"), &zstd::encode_all(page(7..9).as_bytes(), 0).unwrap());
+
+    let mut source = FileSource::new(vec![dir.clone()]).unwrap();
+    let read = display_orders(&mut source).await;
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(read, (0..9).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn resumes_partway_through_a_file() {
+    let dir = temp_dir("file-source-resume");
+    // More than one page of lines, so there's a cursor partway through the file
+    write(&dir.join("items.ndjson.gz"), &gzip(&ndjson(0..1500)));
+    write(&dir.join("more.ndjson"), ndjson(1500..1510).as_bytes());
+
+    let mut source = FileSource::new(vec![dir.clone()]).unwrap();
+    let first_page = source.next_page().await.unwrap().unwrap();
+    let cursor = source.cursor();
+
+    let mut resumed = FileSource::new(vec![dir.clone()]).unwrap();
+    resumed.resume(cursor).unwrap();
+    let rest = display_orders(&mut resumed).await;
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(first_page.len(), 1000);
+    assert_eq!(rest, (1000..1510).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn unknown_extension_is_an_error() {
+    let dir = temp_dir("file-source-extension");
+    write(&dir.join("items.txt"), ndjson(0..1).as_bytes());
+
+    let mut source = FileSource::new(vec![dir.join("items.txt")]).unwrap();
+    let err = source.next_page().await.unwrap_err();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(err.to_string().contains("Can't tell what format"));
+}
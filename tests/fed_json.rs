@@ -0,0 +1,143 @@
+mod common;
+
+use fed2::chron_schema::RunnerDesc;
+use fed2::fed_schema::*;
+use serde_json::json;
+use common::player;
+
+/// Serializes `event`, checks it matches `expected`, then checks it survives a round trip
+fn check(event: Event, expected: serde_json::Value) {
+    let value = serde_json::to_value(&event).unwrap();
+    assert_eq!(value, expected);
+    let round_tripped: Event = serde_json::from_value(value.clone()).unwrap();
+    assert_eq!(serde_json::to_value(&round_tripped).unwrap(), value);
+}
+
+/// A batter's contact, as the first line of a flyout, ground out, hit and so on
+fn contact_by(batter: u128) -> Contact {
+    Contact {
+        batter: player(batter, "Bat Oman"),
+        location: Some(FieldLocation::DeepLeftField),
+        flavor: ContactFlavor::NamedWithSound { sound_effect: SoundEffect::Wham, verb: ContactVerb::Hits },
+    }
+}
+
+/// One of every event type, in the order they're declared
+fn every_event() -> Vec<Event> {
+    vec![
+        Event::PlayBall,
+        Event::BatterUp { batter: player(1, "Bat Oman"), pitcher: Some(player(9, "Pitch Er")) },
+        Event::Ball { flavor: BallFlavor::BallPeriod },
+        Event::Strike { flavor: StrikeFlavor::AdjectiveSwing(SwingAdjective::Pathetic) },
+        Event::Flyout {
+            contact: contact_by(1),
+            defender: player(2, "Glove Person"),
+            flavor: FlyoutFlavor::MakesCatchWithAdjective(CatchAdjective::Diving),
+        },
+        Event::GroundOut {
+            contact: contact_by(1),
+            fielding: Fielding { defender: player(2, "Glove Person"), flavor: FieldingFlavor::ScoopsIt },
+            flavor: GroundoutFlavor::ForcedOutAtFirst,
+            advancements: vec![
+                RunnerAdvancementDesc {
+                    runner: RunnerDesc { id: player(3, "Run Ner").id, name: "Run Ner".to_string(), base: 2, extra: Default::default() },
+                    advancement: RunnerAdvancement::Advanced(Base::Third, AdvancementFlavor::AdvancesTo),
+                },
+                RunnerAdvancementDesc {
+                    runner: RunnerDesc { id: player(4, "Home Body").id, name: "Home Body".to_string(), base: 3, extra: Default::default() },
+                    advancement: RunnerAdvancement::Scored,
+                },
+            ],
+        },
+        Event::Strikeout { batter: player(1, "Bat Oman"), flavor: StrikeoutFlavor::NamedBoth },
+        Event::Foul { flavor: FoulFlavor::FoulTip },
+        Event::HomeRun { contact: contact_by(1), scores: vec![player(3, "Run Ner")] },
+        Event::Hit {
+            contact: contact_by(1),
+            fielding: FailedFielding { defender: player(2, "Glove Person"), flavor: FailedFieldingFlavor::BobblesIt }.into(),
+            hit_type: HitType::Double,
+            flavor: HitFlavor::IsOnWith,
+            advancements: vec![Advancement { runner: player(3, "Run Ner"), to_base: Base::Third }],
+            scores: vec![player(4, "Home Body")],
+        },
+        Event::Walk { batter: player(1, "Bat Oman"), flavor: WalkFlavor::Ball4 },
+        Event::EndOfHalfInning { top_of_inning: false, inning: 8 },
+        Event::FieldersChoice {
+            contact: contact_by(1),
+            fielding: Fielding { defender: player(2, "Glove Person"), flavor: FieldingFlavor::GetsIt },
+            runner_out: RunnerDesc { id: player(3, "Run Ner").id, name: "Run Ner".to_string(), base: 1, extra: Default::default() },
+        },
+    ]
+}
+
+#[test]
+fn unit_variant() {
+    check(Event::PlayBall, json!({"type": "play_ball"}));
+}
+
+#[test]
+fn flavored_pitch() {
+    check(
+        Event::Ball { flavor: BallFlavor::Adjective(PitchAdjective::WellLocated) },
+        json!({"type": "ball", "flavor": {"adjective": "well_located"}}),
+    );
+    check(Event::Ball { flavor: BallFlavor::BallPeriod }, json!({"type": "ball", "flavor": "ball_period"}));
+    check(
+        Event::Strike { flavor: StrikeFlavor::AdjectiveSwing(SwingAdjective::Pathetic) },
+        json!({"type": "strike", "flavor": {"adjective_swing": "pathetic"}}),
+    );
+    check(Event::Strike { flavor: StrikeFlavor::Looking }, json!({"type": "strike", "flavor": "looking"}));
+    check(Event::Foul { flavor: FoulFlavor::FoulTip }, json!({"type": "foul", "flavor": "foul_tip"}));
+}
+
+#[test]
+fn contact() {
+    check(
+        Event::Flyout {
+            contact: contact_by(1),
+            defender: player(2, "Glove Person"),
+            flavor: FlyoutFlavor::FlyOutTo,
+        },
+        json!({
+            "type": "flyout",
+            "contact": {
+                "batter": {"id": "00000000-0000-0000-0000-000000000001", "name": "Bat Oman"},
+                "location": "deep_left_field",
+                "flavor": {"named_with_sound": {"sound_effect": "wham", "verb": "hits"}},
+            },
+            "defender": {"id": "00000000-0000-0000-0000-000000000002", "name": "Glove Person"},
+            "flavor": "fly_out_to",
+        }),
+    );
+}
+
+#[test]
+fn every_event_type_round_trips() {
+    let events = every_event();
+    let types: Vec<_> = events.iter()
+        .map(|event| serde_json::to_value(event).unwrap()["type"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(types, [
+        "play_ball", "batter_up", "ball", "strike", "flyout", "ground_out", "strikeout", "foul",
+        "home_run", "hit", "walk", "end_of_half_inning", "fielders_choice",
+    ]);
+
+    for event in events {
+        let value = serde_json::to_value(&event).unwrap();
+        let round_tripped: Event = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&round_tripped).unwrap(), value);
+    }
+}
+
+#[test]
+fn json_schema_covers_every_event_type() {
+    let schema = serde_json::to_value(fed2::fed_schema::json_schema()).unwrap();
+    let types: Vec<_> = schema["$defs"]["Event"]["oneOf"].as_array().unwrap().iter()
+        .map(|variant| variant["properties"]["type"]["const"].as_str().unwrap())
+        .collect();
+    assert_eq!(types, [
+        "play_ball", "batter_up", "ball", "strike", "flyout", "ground_out", "strikeout", "foul",
+        "home_run", "hit", "walk", "end_of_half_inning", "fielders_choice",
+    ]);
+    assert!(schema["$defs"]["BallFlavor"].is_object());
+}
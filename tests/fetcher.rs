@@ -0,0 +1,242 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use fed2::chronicler::{ChroniclerClient, ChroniclerConfig, GameEventsQuery};
+use fed2::event_source::EventSource;
+use fed2::fetcher::{FetchError, FixtureMode, GameEventsFetcher, RetryPolicy};
+
+const PAGE: &str = r#"{"items": [{"game_id": "11111111-1111-1111-1111-111111111111", "timestamp": "2023-01-01T00:00:00Z", "data": {"changedState": {"started": true}, "displayDelay": 5, "displayOrder": 0, "displayText": "Play Ball!", "displayTime": "2023-01-01T00:00:00Z"}}], "next_page": "abc"}"#;
+const SECOND_PAGE: &str = r#"{"items": [{"game_id": "11111111-1111-1111-1111-111111111111", "timestamp": "2023-01-01T00:00:05Z", "data": {"changedState": {"batter": null}, "displayDelay": 5, "displayOrder": 1, "displayText": "", "displayTime": "2023-01-01T00:00:05Z"}}], "next_page": "def"}"#;
+const CAUGHT_UP_PAGE: &str = r#"{"items": [{"game_id": "11111111-1111-1111-1111-111111111111", "timestamp": "2023-01-01T00:00:05Z", "data": {"changedState": {"batter": null}, "displayDelay": 5, "displayOrder": 1, "displayText": "", "displayTime": "2023-01-01T00:00:05Z"}}], "next_page": null}"#;
+const LAST_PAGE: &str = r#"{"items": [], "next_page": null}"#;
+
+/// A stand-in for chronicler that serves `responses` in order, one per request, then 404s.
+/// Returns its base URL and the request paths it has seen so far.
+fn serve(responses: Vec<(u16, &'static str)>) -> (String, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}/chronicler/v0/", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+
+    let seen = requests.clone();
+    std::thread::spawn(move || {
+        let mut responses = responses.into_iter();
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            // Skip the headers. GET requests don't have a body.
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                if header.trim().is_empty() { break; }
+            }
+            seen.lock().unwrap().push(request_line.split(' ').nth(1).unwrap_or_default().to_string());
+
+            let (status, body) = responses.next().unwrap_or((404, ""));
+            write!(stream, "HTTP/1.1 {status} Whatever\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}", body.len()).unwrap();
+        }
+    });
+
+    (base_url, requests)
+}
+
+fn fetcher(base_url: &str) -> GameEventsFetcher {
+    GameEventsFetcher::new(ChroniclerClient::new(base_url).unwrap(), GameEventsQuery::default(), None, FixtureMode::Live)
+        .retry(RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(10),
+        })
+}
+
+#[tokio::test]
+async fn follows_next_page_until_out_of_items() {
+    let (base_url, requests) = serve(vec![(200, PAGE), (200, SECOND_PAGE), (200, LAST_PAGE)]);
+    let mut fetcher = GameEventsFetcher::new(ChroniclerClient::new(&base_url).unwrap(), GameEventsQuery::default(), None, FixtureMode::Live);
+
+    let mut display_orders = Vec::new();
+    while let Some(page) = fetcher.next_page().await.unwrap() {
+        display_orders.extend(page.iter().map(|item| item.data.display_order));
+    }
+    assert_eq!(display_orders, [0, 1]);
+    assert_eq!(*requests.lock().unwrap(), [
+        "/chronicler/v0/game-events?count=1000",
+        "/chronicler/v0/game-events?count=1000&page=abc",
+        "/chronicler/v0/game-events?count=1000&page=def",
+    ]);
+}
+
+#[tokio::test]
+async fn stops_at_the_page_limit() {
+    let (base_url, requests) = serve(vec![(200, PAGE), (200, SECOND_PAGE), (200, LAST_PAGE)]);
+    let mut fetcher = GameEventsFetcher::new(ChroniclerClient::new(&base_url).unwrap(), GameEventsQuery::default(), Some(2), FixtureMode::Live);
+
+    assert_eq!(fetcher.next_page().await.unwrap().unwrap().len(), 1);
+    assert_eq!(fetcher.next_page().await.unwrap().unwrap().len(), 1);
+    assert!(fetcher.next_page().await.unwrap().is_none());
+    assert_eq!(requests.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn tail_polls_after_the_last_item() {
+    let (base_url, requests) = serve(vec![(200, PAGE), (200, CAUGHT_UP_PAGE), (200, LAST_PAGE), (200, LAST_PAGE)]);
+    let mut fetcher = fetcher(&base_url).tail(Duration::from_millis(1));
+
+    for expected_items in [1, 1, 0, 0] {
+        assert_eq!(fetcher.next_page().await.unwrap().unwrap().len(), expected_items);
+    }
+    // Once chronicler stops giving page tokens, the cursor is the last item's timestamp
+    assert_eq!(*requests.lock().unwrap(), [
+        "/chronicler/v0/game-events?count=1000",
+        "/chronicler/v0/game-events?count=1000&page=abc",
+        "/chronicler/v0/game-events?count=1000&after=2023-01-01T00%3A00%3A05%2B00%3A00",
+        "/chronicler/v0/game-events?count=1000&after=2023-01-01T00%3A00%3A05%2B00%3A00",
+    ]);
+}
+
+#[tokio::test]
+async fn retries_server_errors() {
+    let (base_url, requests) = serve(vec![(503, ""), (500, ""), (200, PAGE), (200, LAST_PAGE)]);
+    let mut fetcher = fetcher(&base_url);
+
+    let page = fetcher.next_page().await.unwrap().unwrap();
+    assert_eq!(page.len(), 1);
+    assert_eq!(requests.lock().unwrap().len(), 3);
+
+    assert!(fetcher.next_page().await.unwrap().unwrap().is_empty());
+    assert!(fetcher.next_page().await.unwrap().is_none());
+    assert_eq!(requests.lock().unwrap().last().unwrap(), "/chronicler/v0/game-events?count=1000&page=abc");
+}
+
+#[tokio::test]
+async fn gives_up_with_the_page_token() {
+    let (base_url, requests) = serve(vec![(200, PAGE), (502, ""), (503, ""), (504, "")]);
+    let mut fetcher = fetcher(&base_url);
+
+    fetcher.next_page().await.unwrap().unwrap();
+    let err = fetcher.next_page().await.unwrap_err();
+    let err = err.downcast_ref::<FetchError>().unwrap();
+    assert!(matches!(err, FetchError::RetriesExhausted { attempts: 3, .. }));
+    assert_eq!(err.page(), Some("abc"));
+    assert_eq!(requests.lock().unwrap().len(), 4);
+}
+
+#[tokio::test]
+async fn gives_up_with_the_time_when_tailing_without_a_page_token() {
+    let (base_url, requests) = serve(vec![(200, CAUGHT_UP_PAGE), (502, ""), (503, ""), (504, "")]);
+    let mut fetcher = fetcher(&base_url).tail(Duration::from_millis(1));
+
+    fetcher.next_page().await.unwrap().unwrap();
+    let err = fetcher.next_page().await.unwrap_err();
+    let err = err.downcast_ref::<FetchError>().unwrap();
+    assert_eq!(err.page(), None);
+    assert_eq!(err.after(), Some("2023-01-01T00:00:05Z".parse().unwrap()));
+    assert_eq!(err.to_string(), "Gave up fetching the game events newer than 2023-01-01T00:00:05+00:00 after 3 attempts");
+    assert_eq!(requests.lock().unwrap().len(), 4);
+
+    // Which is where a run started with that as `after` begins
+    let (base_url, requests) = serve(vec![(200, LAST_PAGE)]);
+    let query = GameEventsQuery { after: err.after(), ..GameEventsQuery::default() };
+    let mut resumed = GameEventsFetcher::new(ChroniclerClient::new(&base_url).unwrap(), query, None, FixtureMode::Live);
+    resumed.next_page().await.unwrap().unwrap();
+    assert_eq!(*requests.lock().unwrap(), ["/chronicler/v0/game-events?count=1000&after=2023-01-01T00%3A00%3A05%2B00%3A00"]);
+}
+
+#[tokio::test]
+async fn does_not_retry_client_errors() {
+    let (base_url, requests) = serve(vec![(400, "")]);
+    let mut fetcher = fetcher(&base_url);
+
+    let err = fetcher.next_page().await.unwrap_err();
+    let err = err.downcast_ref::<FetchError>().unwrap();
+    assert!(matches!(err, FetchError::Permanent { .. }));
+    assert_eq!(err.page(), None);
+    assert_eq!(requests.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn does_not_retry_malformed_pages() {
+    let (base_url, requests) = serve(vec![(200, PAGE), (200, "{\"items\": [")]);
+    let mut fetcher = fetcher(&base_url);
+
+    fetcher.next_page().await.unwrap().unwrap();
+    let err = fetcher.next_page().await.unwrap_err();
+    let err = err.downcast_ref::<FetchError>().unwrap();
+    assert!(matches!(err, FetchError::Permanent { .. }));
+    assert_eq!(err.page(), Some("abc"));
+    assert_eq!(requests.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn rate_limits_requests() {
+    let (base_url, _) = serve(vec![(500, ""), (500, ""), (200, PAGE)]);
+    let mut fetcher = fetcher(&base_url).rate_limit(20.).unwrap();
+
+    let start = std::time::Instant::now();
+    fetcher.next_page().await.unwrap().unwrap();
+    // Three requests at 20 per second need at least two 50ms gaps
+    assert!(start.elapsed() >= Duration::from_millis(100));
+}
+
+#[tokio::test]
+async fn replays_recorded_responses() {
+    let dir = std::env::temp_dir().join(format!("fed2-fixtures-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let (base_url, requests) = serve(vec![(200, PAGE), (200, LAST_PAGE)]);
+    let client = ChroniclerClient::new(&base_url).unwrap();
+
+    let mut recording = GameEventsFetcher::new(client.clone(), GameEventsQuery::default(), None, FixtureMode::Record(dir.clone()));
+    let mut recorded = Vec::new();
+    while let Some(page) = recording.next_page().await.unwrap() {
+        recorded.push(serde_json::to_string(&page).unwrap());
+    }
+    assert_eq!(requests.lock().unwrap().len(), 2);
+    // Bodies are saved exactly as they were served
+    let mut bodies: Vec<_> = std::fs::read_dir(&dir).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .map(|path| std::fs::read_to_string(path).unwrap())
+        .collect();
+    bodies.sort();
+    let mut served = vec![PAGE, LAST_PAGE];
+    served.sort();
+    assert_eq!(bodies, served);
+
+    // The stand-in server would 404 from here on, so these have to come from the fixtures
+    let mut replaying = GameEventsFetcher::new(client.clone(), GameEventsQuery::default(), None, FixtureMode::Replay(dir.clone()));
+    let mut replayed = Vec::new();
+    while let Some(page) = replaying.next_page().await.unwrap() {
+        replayed.push(serde_json::to_string(&page).unwrap());
+    }
+    assert_eq!(replayed, recorded);
+    assert_eq!(requests.lock().unwrap().len(), 2);
+
+    // A request that wasn't recorded can't be replayed
+    let query = GameEventsQuery { count: 10, ..GameEventsQuery::default() };
+    let mut unrecorded = GameEventsFetcher::new(client, query, None, FixtureMode::Replay(dir.clone()));
+    let err = unrecorded.next_page().await.unwrap_err();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(matches!(err.downcast_ref::<FetchError>(), Some(FetchError::Permanent { .. })));
+    assert_eq!(requests.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn rate_limit_has_to_be_positive() {
+    let client = ChroniclerClient::new("http://localhost/").unwrap();
+    for requests_per_second in [0., -1., f64::NAN, f64::INFINITY, 1e-300] {
+        let fetcher = GameEventsFetcher::new(client.clone(), GameEventsQuery::default(), None, FixtureMode::Live);
+        assert!(fetcher.rate_limit(requests_per_second).is_err(), "{requests_per_second} was allowed");
+    }
+
+    let path = std::env::temp_dir().join(format!("fed2-config-{}.toml", std::process::id()));
+    std::fs::write(&path, "requests_per_second = 0.0\nmax_pages = 3\n").unwrap();
+    let err = ChroniclerConfig::load(&path).unwrap_err();
+    std::fs::write(&path, "requests_per_second = 2.5\nmax_pages = 3\n").unwrap();
+    let config = ChroniclerConfig::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(format!("{err:#}").contains("requests_per_second"));
+    assert_eq!((config.requests_per_second, config.max_pages), (Some(2.5), Some(3)));
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use fed2::fed_schema::{Event, FedEvent, SCHEMA_VERSION};
+use fed2::migrations::{upgrade, MigrationError};
+use serde_json::{json, Value};
+use common::{game, player};
+
+/// `common::game` as a version 1 build would have written it, kept as text so it stays version 1
+/// JSON whatever happens to the types
+const V1_GAME: &str = r#"{"display_time":"2020-09-13T12:26:40Z","event":{"type":"play_ball"},"first_display_order":0,"game_id":"00000000-0000-0000-0000-000000000064","id":"fdfe5850-a0a5-5f03-872d-82c651edd6ee","last_display_order":0,"sequence":0,"timestamp":"2020-09-13T12:26:40Z"}
+{"display_time":"2020-09-13T12:26:41Z","event":{"batter":{"id":"00000000-0000-0000-0000-000000000001","name":"Walker"},"pitcher":{"id":"00000000-0000-0000-0000-000000000003","name":"Pitcher"},"type":"batter_up"},"first_display_order":1,"game_id":"00000000-0000-0000-0000-000000000064","id":"9b9144ea-e110-50f9-a58b-da3cc7cbc386","last_display_order":1,"sequence":1,"timestamp":"2020-09-13T12:26:41Z"}
+{"display_time":"2020-09-13T12:26:42Z","event":{"flavor":{"adjective":"well_located"},"type":"ball"},"first_display_order":2,"game_id":"00000000-0000-0000-0000-000000000064","id":"bfa5affe-baae-5500-844a-c7a3298b2853","last_display_order":2,"sequence":2,"timestamp":"2020-09-13T12:26:42Z"}
+{"display_time":"2020-09-13T12:26:43Z","event":{"batter":{"id":"00000000-0000-0000-0000-000000000001","name":"Walker"},"flavor":"draws_walk","type":"walk"},"first_display_order":3,"game_id":"00000000-0000-0000-0000-000000000064","id":"c894b67c-13ea-58ae-b28a-db42e1e43a4a","last_display_order":3,"sequence":3,"timestamp":"2020-09-13T12:26:43Z"}
+{"display_time":"2020-09-13T12:26:44Z","event":{"batter":{"id":"00000000-0000-0000-0000-000000000002","name":"Slugger"},"pitcher":{"id":"00000000-0000-0000-0000-000000000003","name":"Pitcher"},"type":"batter_up"},"first_display_order":4,"game_id":"00000000-0000-0000-0000-000000000064","id":"3000d8fa-1dc9-5098-8c1f-a0ed2c94489f","last_display_order":4,"sequence":4,"timestamp":"2020-09-13T12:26:44Z"}
+{"display_time":"2020-09-13T12:26:45Z","event":{"flavor":"looking","type":"strike"},"first_display_order":5,"game_id":"00000000-0000-0000-0000-000000000064","id":"0c0aabd3-01ed-5502-8de6-72d25e200392","last_display_order":5,"sequence":5,"timestamp":"2020-09-13T12:26:45Z"}
+{"display_time":"2020-09-13T12:26:46Z","event":{"contact":{"batter":{"id":"00000000-0000-0000-0000-000000000002","name":"Slugger"},"flavor":{"named_with_sound":{"sound_effect":"wham","verb":"hits"}},"location":"deep_left_field"},"type":"home_run"},"first_display_order":6,"game_id":"00000000-0000-0000-0000-000000000064","id":"f982e249-56d1-5f49-9a41-bce989bf41f3","last_display_order":6,"sequence":6,"timestamp":"2020-09-13T12:26:46Z"}"#;
+
+fn to_values(events: &[FedEvent]) -> Vec<Value> {
+    events.iter().map(|event| serde_json::to_value(event).unwrap()).collect()
+}
+
+/// What a version 1 build wrote: no `schema_version`, and no `scores` on home runs
+fn as_v1(events: &[FedEvent]) -> Vec<Value> {
+    let mut values = to_values(events);
+    for value in &mut values {
+        let object = value.as_object_mut().unwrap();
+        object.remove("schema_version");
+        object["event"].as_object_mut().unwrap().remove("scores");
+    }
+    values
+}
+
+#[test]
+fn v1_home_run_gets_scores_from_replay() {
+    let mut v1 = as_v1(&game());
+    // Out of order on purpose
+    v1.reverse();
+
+    let upgraded = upgrade(v1).unwrap();
+    assert_eq!(to_values(&upgraded), to_values(&game()));
+    assert!(upgraded.iter().all(|event| event.schema_version == SCHEMA_VERSION));
+    assert!(matches!(&upgraded[6].event, Event::HomeRun { scores, .. } if scores == &[player(1, "Walker")]));
+}
+
+#[test]
+fn current_events_are_unchanged() {
+    let events = to_values(&game());
+    // Doesn't need the start of the game
+    let upgraded = upgrade(events[3..].to_vec()).unwrap();
+    assert_eq!(to_values(&upgraded), events[3..]);
+}
+
+#[test]
+fn newer_version_is_rejected() {
+    let mut events = to_values(&game());
+    events[2]["schema_version"] = json!(SCHEMA_VERSION + 1);
+    assert!(matches!(upgrade(events), Err(MigrationError::UnsupportedVersion { found }) if found == SCHEMA_VERSION + 1));
+}
+
+#[test]
+fn v1_game_missing_events_is_rejected() {
+    let mut v1 = as_v1(&game());
+    v1.remove(2);
+    assert!(matches!(upgrade(v1), Err(MigrationError::IncompleteGame { expected: 2, .. })));
+}
+
+#[test]
+fn saved_v1_game_upgrades() {
+    let v1 = V1_GAME.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    let upgraded = upgrade(v1).unwrap();
+    assert_eq!(to_values(&upgraded), to_values(&game()));
+}
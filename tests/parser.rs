@@ -0,0 +1,59 @@
+mod common;
+
+use chrono::{TimeZone, Utc};
+use fed2::chron_schema::GameUpdateDelta;
+use fed2::fed_schema::Event;
+use fed2::parser::{CheckpointError, Parser};
+use fed2::synthesizer::{synthesize_game, Rosters};
+use common::game;
+
+fn updates() -> Vec<GameUpdateDelta> {
+    let events: Vec<_> = game().into_iter().map(|event| event.event).collect();
+    let rosters = Rosters { home_defenders: vec![], away_defenders: vec![] };
+    synthesize_game(&events, rosters, Utc.timestamp_opt(1_600_000_000, 0).unwrap()).unwrap()
+}
+
+fn parse_all(parser: &mut Parser, updates: impl IntoIterator<Item = GameUpdateDelta>) -> Vec<Event> {
+    updates.into_iter().filter_map(|update| parser.parse(update).unwrap().0).collect()
+}
+
+#[test]
+fn checkpoint_round_trip() {
+    let updates = updates();
+    let uninterrupted = parse_all(&mut Parser::default(), updates.clone());
+    assert_eq!(uninterrupted.len(), game().len());
+
+    // Stop just after the home run's contact line, while the parser is holding the `Contact`
+    let contact = updates.iter().position(|update| update.display_text.starts_with("WHAM!")).unwrap();
+    let mut parser = Parser::default();
+    let mut resumed = parse_all(&mut parser, updates[..=contact].to_vec());
+    let checkpoint = parser.checkpoint().unwrap();
+
+    let mut parser = Parser::from_checkpoint(&checkpoint).unwrap();
+    assert_eq!(parser.checkpoint().unwrap(), checkpoint);
+    resumed.extend(parse_all(&mut parser, updates[contact + 1..].to_vec()));
+
+    assert_eq!(serde_json::to_value(&resumed).unwrap(), serde_json::to_value(&uninterrupted).unwrap());
+}
+
+#[test]
+fn stale_checkpoint_is_rejected() {
+    let mut checkpoint: serde_json::Value = serde_json::from_slice(&Parser::default().checkpoint().unwrap()).unwrap();
+    let current = checkpoint["version"].as_u64().unwrap() as u32;
+    checkpoint["version"] = (current - 1).into();
+
+    let err = Parser::from_checkpoint(&serde_json::to_vec(&checkpoint).unwrap()).unwrap_err();
+    assert!(matches!(err, CheckpointError::UnsupportedVersion { found, expected } if found == current - 1 && expected == current));
+}
+
+#[test]
+fn corrupt_checkpoint_is_rejected() {
+    for corrupt in [&b"not json"[..], b"{\"parser\": {}}", b"{\"version\": \"3\"}"] {
+        assert!(matches!(Parser::from_checkpoint(corrupt), Err(CheckpointError::Malformed(_))));
+    }
+
+    // A good header doesn't make up for a bad body
+    let mut checkpoint: serde_json::Value = serde_json::from_slice(&Parser::default().checkpoint().unwrap()).unwrap();
+    checkpoint["parser"]["state"] = "nonsense".into();
+    assert!(matches!(Parser::from_checkpoint(&serde_json::to_vec(&checkpoint).unwrap()), Err(CheckpointError::Malformed(_))));
+}
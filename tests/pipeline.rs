@@ -0,0 +1,219 @@
+mod common;
+
+use std::path::PathBuf;
+use std::time::Duration as StdDuration;
+use std::sync::{Arc, Mutex};
+use chrono::{Duration, TimeZone, Utc};
+use fed2::chron_schema::{ExtraFields, GameResponse, GameUpdateDelta};
+use fed2::event_source::FileSource;
+use fed2::pipeline::{self, CheckpointOptions, GameOutput, OutputSink, ParsedEvent, PipelineOptions};
+use fed2::fed_schema::{Event, StrikeoutFlavor};
+use fed2::synthesizer::{synthesize_game, Rosters};
+use common::GAME;
+use serde_json::json;
+
+/// The updates chronicler would have served for `common::game`
+fn updates() -> Vec<GameUpdateDelta> {
+    let events: Vec<_> = common::game().into_iter().map(|event| event.event).collect();
+    let rosters = Rosters { home_defenders: vec![], away_defenders: vec![] };
+    synthesize_game(&events, rosters, Utc.timestamp_opt(1_600_000_000, 0).unwrap()).unwrap()
+}
+
+/// `updates` as chronicler items for `GAME`
+fn items(updates: Vec<GameUpdateDelta>) -> Vec<GameResponse> {
+    updates.into_iter()
+        .map(|update| GameResponse { game_id: GAME, timestamp: update.display_time, data: update, extra: ExtraFields::default() })
+        .collect()
+}
+
+/// Saves `items` as NDJSON, returning the file's path
+fn write_ndjson(name: &str, items: Vec<GameResponse>) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("fed2-{name}-{}.ndjson", std::process::id()));
+    let lines: String = items.into_iter()
+        .map(|item| serde_json::to_string(&item).unwrap() + "\n")
+        .collect();
+    std::fs::write(&path, lines).unwrap();
+    path
+}
+
+/// Runs the pipeline over `path`, returning the events it wrote
+async fn run(path: &PathBuf) -> anyhow::Result<Vec<ParsedEvent>> {
+    let options = PipelineOptions { reorder_window: Duration::seconds(60), checkpoint: None };
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let written = events.clone();
+    let result = pipeline::run(FileSource::new(vec![path.clone()])?, options, move |output: GameOutput| {
+        written.lock().unwrap().extend(output.event);
+        Ok(())
+    }).await;
+    std::fs::remove_file(path).unwrap();
+    result?;
+    let events = std::mem::take(&mut *events.lock().unwrap());
+    Ok(events)
+}
+
+#[tokio::test]
+async fn parses_saved_updates() {
+    let events = run(&write_ndjson("pipeline", items(updates()))).await.unwrap();
+    let events: Vec<_> = events.into_iter().map(|parsed| parsed.event).collect();
+
+    let parsed: Vec<_> = events.iter().map(|event| &event.event).collect();
+    let expected: Vec<_> = common::game().into_iter().map(|event| event.event).collect();
+    assert_eq!(serde_json::to_value(parsed).unwrap(), serde_json::to_value(expected).unwrap());
+    assert!(events.iter().all(|event| event.game_id == GAME));
+    assert_eq!(events.iter().map(|event| event.sequence).collect::<Vec<_>>(), (0..7).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn unknown_fields_are_passed_on() {
+    let mut items = items(updates());
+    // The home run is the last event, and takes three updates
+    let home_run = items.iter().position(|item| item.data.display_text.starts_with("WHAM!")).unwrap();
+    items[home_run].extra.insert("season".to_string(), json!(3));
+    items[home_run].data.changed_state.extra.insert("weather".to_string(), json!(7));
+    items[home_run + 1].extra.insert("season".to_string(), json!(4));
+    items[home_run + 1].data.extra.insert("isPostseason".to_string(), json!(false));
+
+    let events = run(&write_ndjson("pipeline-unknown-fields", items)).await.unwrap();
+    let (home_run, others) = events.split_last().unwrap();
+    assert!(others.iter().all(|parsed| parsed.extra.is_empty()));
+    let home_run = serde_json::to_value(home_run).unwrap();
+    assert_eq!(home_run["event"]["type"], "home_run");
+    assert_eq!(home_run["extra"], json!({ "season": 4, "data.isPostseason": false, "data.changedState.weather": 7 }));
+    // Events without any are written the same as before
+    assert!(serde_json::to_value(&others[0]).unwrap().get("extra").is_none());
+}
+
+#[tokio::test]
+async fn unparseable_update_names_its_game() {
+    let mut updates = updates();
+    let ball = updates.iter_mut().find(|update| update.display_text.contains("Ball, 1-0")).unwrap();
+    ball.display_text = ball.display_text.replace("1-0", "2-0");
+
+    let err = run(&write_ndjson("pipeline-unparseable", items(updates))).await.unwrap_err();
+    let message = format!("{err:#}");
+    assert!(message.contains(&GAME.to_string()), "{message}");
+}
+
+/// Writes each event as a line of JSON to a shared buffer, optionally failing partway through to
+/// stand in for a run that dies
+struct Buffer {
+    bytes: Arc<Mutex<Vec<u8>>>,
+    fail_after: Option<usize>,
+}
+
+impl OutputSink for Buffer {
+    fn write(&mut self, output: GameOutput) -> anyhow::Result<()> {
+        if let Some(fail_after) = &mut self.fail_after {
+            if *fail_after == 0 {
+                return Err(anyhow::anyhow!("Ran out of room"));
+            }
+            *fail_after -= 1;
+        }
+        if let Some(parsed) = output.event {
+            let event = parsed.event;
+            let mut bytes = self.bytes.lock().unwrap();
+            serde_json::to_writer(&mut *bytes, &event)?;
+            bytes.push(b'\n');
+        }
+        Ok(())
+    }
+
+    fn position(&mut self) -> anyhow::Result<Option<u64>> {
+        Ok(Some(self.bytes.lock().unwrap().len() as u64))
+    }
+
+    fn rewind(&mut self, position: u64) -> anyhow::Result<()> {
+        self.bytes.lock().unwrap().truncate(position as usize);
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn resumed_run_writes_the_same_output() {
+    // Each file is a page, and each page ends a batch, so there's a checkpoint between each
+    let paths: Vec<_> = updates().chunks(4).enumerate()
+        .map(|(i, chunk)| write_ndjson(&format!("pipeline-resume-{i}"), items(chunk.to_vec())))
+        .collect();
+    let checkpoint_path = std::env::temp_dir().join(format!("fed2-pipeline-resume-{}.checkpoint", std::process::id()));
+    let options = |resume| PipelineOptions {
+        reorder_window: Duration::seconds(60),
+        checkpoint: Some(CheckpointOptions { path: checkpoint_path.clone(), interval: StdDuration::ZERO, resume }),
+    };
+
+    let uninterrupted = Arc::new(Mutex::new(Vec::new()));
+    let sink = Buffer { bytes: uninterrupted.clone(), fail_after: None };
+    pipeline::run(FileSource::new(paths.clone()).unwrap(), options(false), sink).await.unwrap();
+
+    // Dies partway through a batch, after writing some of the outputs that came after the last
+    // checkpoint
+    let interrupted = Arc::new(Mutex::new(Vec::new()));
+    let sink = Buffer { bytes: interrupted.clone(), fail_after: Some(8) };
+    pipeline::run(FileSource::new(paths.clone()).unwrap(), options(false), sink).await.unwrap_err();
+    let sink = Buffer { bytes: interrupted.clone(), fail_after: None };
+    pipeline::run(FileSource::new(paths.clone()).unwrap(), options(true), sink).await.unwrap();
+
+    for path in paths.iter().chain([&checkpoint_path]) {
+        std::fs::remove_file(path).unwrap();
+    }
+    let uninterrupted = uninterrupted.lock().unwrap();
+    assert_eq!(uninterrupted.iter().filter(|&&byte| byte == b'\n').count(), 7);
+    assert_eq!(String::from_utf8_lossy(&interrupted.lock().unwrap()), String::from_utf8_lossy(&uninterrupted));
+}
+
+/// The updates for a whole game: the walk and home run from `common::game`, then everyone strikes
+/// out until the away team wins 2-0 at the end of the 9th
+fn whole_game_updates() -> Vec<GameUpdateDelta> {
+    let mut events: Vec<_> = common::game().into_iter().map(|event| event.event).collect();
+    let batter = common::player(4, "Whiffer");
+    let pitcher = common::player(3, "Pitcher");
+    let three_outs = |events: &mut Vec<Event>| {
+        for _ in 0..3 {
+            events.push(Event::BatterUp { batter: batter.clone(), pitcher: Some(pitcher.clone()) });
+            events.push(Event::Strikeout { batter: batter.clone(), flavor: StrikeoutFlavor::NamedBatter });
+        }
+    };
+    three_outs(&mut events);
+    events.push(Event::EndOfHalfInning { top_of_inning: true, inning: 0 });
+    for inning in 0..9 {
+        if inning > 0 {
+            three_outs(&mut events);
+            events.push(Event::EndOfHalfInning { top_of_inning: true, inning });
+        }
+        three_outs(&mut events);
+        events.push(Event::EndOfHalfInning { top_of_inning: false, inning });
+    }
+
+    let rosters = Rosters { home_defenders: vec![], away_defenders: vec![] };
+    synthesize_game(&events, rosters, Utc.timestamp_opt(1_600_000_000, 0).unwrap()).unwrap()
+}
+
+#[tokio::test]
+async fn finished_games_are_dropped_from_the_checkpoint() {
+    let mut updates = whole_game_updates();
+    // Chronicler can keep sending updates once the game is over, which have nothing to parse
+    let mut late = updates.last().unwrap().clone();
+    late.display_order += 1;
+    late.display_time += Duration::seconds(5);
+    late.display_text = "Away team 2, Home team 0".to_string();
+    updates.push(late);
+
+    let path = write_ndjson("pipeline-game-over", items(updates));
+    let checkpoint_path = std::env::temp_dir().join(format!("fed2-pipeline-game-over-{}.checkpoint", std::process::id()));
+    let options = PipelineOptions {
+        reorder_window: Duration::seconds(60),
+        checkpoint: Some(CheckpointOptions { path: checkpoint_path.clone(), interval: StdDuration::ZERO, resume: false }),
+    };
+    let bytes = Arc::new(Mutex::new(Vec::new()));
+    let sink = Buffer { bytes: bytes.clone(), fail_after: None };
+    let result = pipeline::run(FileSource::new(vec![path.clone()]).unwrap(), options, sink).await;
+    let checkpoint: serde_json::Value = serde_json::from_slice(&std::fs::read(&checkpoint_path).unwrap()).unwrap();
+    for path in [&path, &checkpoint_path] {
+        std::fs::remove_file(path).unwrap();
+    }
+
+    result.unwrap();
+    let last = bytes.lock().unwrap().split(|&byte| byte == b'\n').rev().nth(1).unwrap().to_vec();
+    let last: serde_json::Value = serde_json::from_slice(&last).unwrap();
+    assert_eq!(last["event"], json!({ "type": "end_of_half_inning", "top_of_inning": false, "inning": 8 }));
+    assert_eq!(checkpoint["games"], json!({}));
+}
@@ -0,0 +1,145 @@
+mod common;
+
+use chrono::{TimeZone, Utc};
+use fed2::chron_schema::RunnerDesc;
+use fed2::fed_schema::*;
+use fed2::proto::{self, write_event, EventReader};
+use prost::Message;
+use uuid::Uuid;
+use common::player;
+
+/// `common::fed_event`, but with sub-second timestamps and a display order range, which the proto
+/// encoding has to keep
+fn fed_event(sequence: u64, event: Event) -> FedEvent {
+    FedEvent {
+        timestamp: Utc.timestamp_opt(1_600_000_000, 123_456_789).unwrap(),
+        display_time: Utc.timestamp_opt(1_600_000_005, 0).unwrap(),
+        last_display_order: sequence as i64 + 1,
+        ..common::fed_event(sequence, event)
+    }
+}
+
+fn contact(location: Option<FieldLocation>) -> Contact {
+    Contact {
+        batter: player(1, "Bat Oman"),
+        location,
+        flavor: ContactFlavor::Named { verb: ContactVerb::Chops, pitch_descriptor: PitchDescriptor::ThePitch },
+    }
+}
+
+/// One of every event type, and both sides of each choice the encoding has to make
+fn events() -> Vec<FedEvent> {
+    let runner = RunnerDesc { id: Uuid::from_u128(3), name: "Run Ner".to_string(), base: 2, extra: Default::default() };
+    vec![
+        fed_event(0, Event::PlayBall),
+        fed_event(1, Event::Ball { flavor: BallFlavor::Adjective(PitchAdjective::WellLocated) }),
+        fed_event(2, Event::Strike { flavor: StrikeFlavor::CaughtLooking }),
+        fed_event(3, Event::Flyout {
+            contact: contact(None),
+            defender: player(2, "Glove Person"),
+            flavor: FlyoutFlavor::MakesCatchWithAdjective(CatchAdjective::Diving),
+        }),
+        fed_event(4, Event::GroundOut {
+            contact: contact(Some(FieldLocation::Infield)),
+            fielding: Fielding { defender: player(2, "Glove Person"), flavor: FieldingFlavor::ScoopsIt },
+            flavor: GroundoutFlavor::ForcedOutAtFirst,
+            advancements: vec![
+                RunnerAdvancementDesc { runner: runner.clone(), advancement: RunnerAdvancement::Advanced(Base::Third, AdvancementFlavor::AdvancesTo) },
+                RunnerAdvancementDesc { runner: runner.clone(), advancement: RunnerAdvancement::Advanced(Base::Second, AdvancementFlavor::To) },
+                RunnerAdvancementDesc { runner: runner.clone(), advancement: RunnerAdvancement::Scored },
+                RunnerAdvancementDesc { runner: runner.clone(), advancement: RunnerAdvancement::None },
+            ],
+        }),
+        fed_event(5, Event::Hit {
+            contact: contact(Some(FieldLocation::DeepRightField)),
+            fielding: MaybeFailedFielding::FailedFielding(FailedFielding { defender: player(2, "Glove Person"), flavor: FailedFieldingFlavor::LosesIt }),
+            hit_type: HitType::Double,
+            flavor: HitFlavor::IsOnWith,
+            advancements: vec![Advancement { runner: player(4, "Other Runner"), to_base: Base::Third }],
+            scores: vec![player(3, "Run Ner")],
+        }),
+        fed_event(6, Event::EndOfHalfInning { top_of_inning: false, inning: 8 }),
+        fed_event(7, Event::BatterUp { batter: player(1, "Bat Oman"), pitcher: Some(player(5, "Pitch Er")) }),
+        fed_event(8, Event::BatterUp { batter: player(1, "Bat Oman"), pitcher: None }),
+        fed_event(9, Event::Foul { flavor: FoulFlavor::FoulsItBack }),
+        fed_event(10, Event::Strikeout { batter: player(1, "Bat Oman"), flavor: StrikeoutFlavor::NamedBoth }),
+        fed_event(11, Event::Walk { batter: player(1, "Bat Oman"), flavor: WalkFlavor::EarnsWalk }),
+        fed_event(12, Event::HomeRun {
+            contact: Contact {
+                batter: player(1, "Bat Oman"),
+                location: Some(FieldLocation::Wall),
+                flavor: ContactFlavor::NamedWithSound { sound_effect: SoundEffect::Crack, verb: ContactVerb::Smacks },
+            },
+            scores: vec![player(3, "Run Ner"), player(4, "Other Runner")],
+        }),
+        fed_event(13, Event::Hit {
+            contact: Contact {
+                batter: player(1, "Bat Oman"),
+                location: Some(FieldLocation::CenterField),
+                flavor: ContactFlavor::Adjective { adjective: ContactAdjective::Solid },
+            },
+            fielding: MaybeFailedFielding::Fielding(Fielding { defender: player(2, "Glove Person"), flavor: FieldingFlavor::RacesIn }),
+            hit_type: HitType::Single,
+            flavor: HitFlavor::Hits,
+            advancements: vec![],
+            scores: vec![],
+        }),
+        fed_event(14, Event::FieldersChoice {
+            contact: contact(Some(FieldLocation::Infield)),
+            fielding: Fielding { defender: player(2, "Glove Person"), flavor: FieldingFlavor::GetsIt },
+            runner_out: RunnerDesc { base: 1, ..runner },
+        }),
+    ]
+}
+
+fn json(events: &[FedEvent]) -> serde_json::Value {
+    serde_json::to_value(events).unwrap()
+}
+
+#[test]
+fn stream_round_trip() {
+    let mut stream = Vec::new();
+    for event in events() {
+        write_event(&mut stream, &event).unwrap();
+    }
+
+    let read = EventReader::new(stream.as_slice()).collect::<anyhow::Result<Vec<_>>>().unwrap();
+    assert_eq!(json(&read), json(&events()));
+}
+
+#[test]
+fn truncated_stream_is_an_error() {
+    let mut stream = Vec::new();
+    write_event(&mut stream, &events()[3]).unwrap();
+    stream.pop();
+
+    let mut reader = EventReader::new(stream.as_slice());
+    assert!(reader.next().unwrap().is_err());
+}
+
+#[test]
+fn every_event_type_is_covered() {
+    let types: std::collections::BTreeSet<_> = json(&events()).as_array().unwrap().iter()
+        .map(|event| event["event"]["type"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(types.len(), 13, "{types:?}");
+}
+
+#[test]
+fn oversized_message_is_an_error() {
+    // A length prefix far past the maximum, which mustn't be allocated before it's rejected
+    let mut stream = Vec::new();
+    prost::encoding::encode_varint(u64::MAX >> 1, &mut stream);
+
+    let mut reader = EventReader::new(stream.as_slice());
+    let err = reader.next().unwrap().unwrap_err();
+    assert!(err.to_string().contains(&proto::MAX_MESSAGE_SIZE.to_string()), "{err}");
+}
+
+#[test]
+fn missing_field_is_an_error() {
+    let mut message = proto::FedEvent::from(&events()[1]);
+    message.event = Some(proto::Event { kind: Some(proto::event::Kind::Ball(proto::event::Ball { flavor: None })) });
+    let decoded = proto::FedEvent::decode(message.encode_to_vec().as_slice()).unwrap();
+    assert!(decoded.to_fed().is_err());
+}
@@ -0,0 +1,107 @@
+mod common;
+
+use fed2::chron_schema::{PlayerDesc, RunnerDesc, State};
+use fed2::fed_schema::*;
+use fed2::reducer::apply;
+use common::{game, player};
+
+fn runner(player: &PlayerDesc, base: i64) -> RunnerDesc {
+    RunnerDesc { id: player.id, name: player.name.clone(), base, extra: Default::default() }
+}
+
+/// The top of the first, with `baserunners` on
+fn state(baserunners: &[RunnerDesc]) -> State {
+    State { started: true, inning: 1, baserunners: baserunners.to_vec(), ..State::default() }
+}
+
+/// Where everyone is, sorted by base
+fn bases(state: &State) -> Vec<(&str, i64)> {
+    let mut bases: Vec<_> = state.baserunners.iter().map(|runner| (runner.name.as_str(), runner.base)).collect();
+    bases.sort_by_key(|(_, base)| *base);
+    bases
+}
+
+fn hit(batter: &PlayerDesc, hit_type: HitType) -> Event {
+    Event::Hit {
+        contact: Contact {
+            batter: batter.clone(),
+            location: None,
+            flavor: ContactFlavor::NamedWithSound { sound_effect: SoundEffect::Crack, verb: ContactVerb::Hits },
+        },
+        fielding: MaybeFailedFielding::Fielding(Fielding { defender: player(9, "Fielder"), flavor: FieldingFlavor::CollectsIt }),
+        hit_type,
+        flavor: HitFlavor::Hits,
+        advancements: vec![],
+        scores: vec![],
+    }
+}
+
+#[test]
+fn walk_with_a_runner_on_first_forces_them_to_second() {
+    let batter = player(1, "Batter");
+    let walk = Event::Walk { batter: batter.clone(), flavor: WalkFlavor::DrawsWalk };
+
+    let mut on_first = state(&[runner(&player(2, "First"), 1)]);
+    apply(&mut on_first, &walk);
+    assert_eq!(bases(&on_first), [("Batter", 1), ("First", 2)]);
+    assert_eq!(on_first.away_score, 0.);
+}
+
+#[test]
+fn walk_forces_only_the_runners_behind_it() {
+    let batter = player(1, "Batter");
+    let walk = Event::Walk { batter: batter.clone(), flavor: WalkFlavor::DrawsWalk };
+
+    let mut first_and_third = state(&[runner(&player(2, "First"), 1), runner(&player(4, "Third"), 3)]);
+    apply(&mut first_and_third, &walk);
+    assert_eq!(bases(&first_and_third), [("Batter", 1), ("First", 2), ("Third", 3)]);
+    assert_eq!(first_and_third.away_score, 0.);
+
+    let mut loaded = state(&[runner(&player(2, "First"), 1), runner(&player(3, "Second"), 2), runner(&player(4, "Third"), 3)]);
+    loaded.balls = 3;
+    apply(&mut loaded, &walk);
+    assert_eq!(bases(&loaded), [("Batter", 1), ("First", 2), ("Second", 3)]);
+    assert_eq!(loaded.away_score, 1.);
+    assert_eq!(loaded.balls, 0);
+}
+
+#[test]
+fn double_forces_a_runner_on_first_to_third() {
+    let batter = player(1, "Batter");
+
+    let mut on_first = state(&[runner(&player(2, "First"), 1)]);
+    apply(&mut on_first, &hit(&batter, HitType::Double));
+    assert_eq!(bases(&on_first), [("Batter", 2), ("First", 3)]);
+    assert_eq!(on_first.away_score, 0.);
+
+    let mut first_and_second = state(&[runner(&player(2, "First"), 1), runner(&player(3, "Second"), 2)]);
+    apply(&mut first_and_second, &hit(&batter, HitType::Double));
+    assert_eq!(bases(&first_and_second), [("Batter", 2), ("First", 3)]);
+    assert_eq!(first_and_second.away_score, 1.);
+}
+
+#[test]
+fn triple_clears_the_bases() {
+    let batter = player(1, "Batter");
+    let mut first_and_second = state(&[runner(&player(2, "First"), 1), runner(&player(3, "Second"), 2)]);
+    first_and_second.top_of_inning = false;
+    apply(&mut first_and_second, &hit(&batter, HitType::Triple));
+    assert_eq!(bases(&first_and_second), [("Batter", 3)]);
+    assert_eq!((first_and_second.home_score, first_and_second.away_score), (2., 0.));
+}
+
+#[test]
+fn home_run_scores_everyone_on_base() {
+    let events = game();
+    let mut state = State::default();
+    for event in &events[..6] {
+        apply(&mut state, &event.event);
+    }
+    assert_eq!(bases(&state), [("Walker", 1)]);
+    assert_eq!(state.strikes, 1);
+
+    apply(&mut state, &events[6].event);
+    assert_eq!(bases(&state), []);
+    assert_eq!((state.away_score, state.strikes, state.outs), (2., 0, 0));
+    assert!(state.batter.is_none());
+}
@@ -0,0 +1,111 @@
+use chrono::{Duration, TimeZone, Utc};
+use fed2::chron_schema::{ExtraFields, GameResponse, GameUpdateDelta, StateDelta};
+use fed2::reorder::{Released, ReorderBuffer};
+use uuid::Uuid;
+
+/// An update with `display_order`, which chronicler saw `seconds` into the game
+fn update(display_order: i64, seconds: i64, text: &str) -> GameResponse {
+    let time = Utc.timestamp_opt(1_600_000_000 + seconds, 0).unwrap();
+    GameResponse {
+        game_id: Uuid::from_u128(1),
+        timestamp: time,
+        data: GameUpdateDelta {
+            changed_state: StateDelta::default(),
+            display_delay: 0,
+            display_order,
+            display_text: text.to_string(),
+            display_time: time,
+            extra: ExtraFields::default(),
+        },
+        extra: ExtraFields::default(),
+    }
+}
+
+/// What was released, as display orders for updates and ranges for gaps
+fn describe(released: Vec<Released>) -> Vec<String> {
+    released.into_iter()
+        .map(|released| match released {
+            Released::Update(item) => { item.data.display_order.to_string() }
+            Released::Gap(display_orders) => { format!("gap {display_orders:?}") }
+        })
+        .collect()
+}
+
+#[test]
+fn releases_updates_in_order() {
+    let mut buffer = ReorderBuffer::new(Duration::seconds(60));
+    assert_eq!(describe(buffer.push(update(0, 0, "a"))), ["0"]);
+    assert_eq!(describe(buffer.push(update(2, 1, "c"))), Vec::<String>::new());
+    assert_eq!(describe(buffer.push(update(3, 2, "d"))), Vec::<String>::new());
+    assert_eq!(describe(buffer.push(update(1, 3, "b"))), ["1", "2", "3"]);
+    assert_eq!(describe(buffer.push(update(4, 4, "e"))), ["4"]);
+}
+
+#[test]
+fn starts_from_the_first_update_seen() {
+    // Joining a game partway through, e.g. when tailing
+    let mut buffer = ReorderBuffer::new(Duration::seconds(60));
+    assert_eq!(describe(buffer.push(update(500, 0, "a"))), ["500"]);
+    assert_eq!(describe(buffer.push(update(502, 1, "c"))), Vec::<String>::new());
+    assert_eq!(describe(buffer.push(update(501, 2, "b"))), ["501", "502"]);
+    // Anything from before the first update seen is too late to parse
+    assert_eq!(describe(buffer.push(update(499, 3, "z"))), Vec::<String>::new());
+    assert_eq!(describe(buffer.flush()), Vec::<String>::new());
+}
+
+#[test]
+fn drops_repeats() {
+    let mut buffer = ReorderBuffer::new(Duration::seconds(60));
+    assert_eq!(describe(buffer.push(update(0, 0, "a"))), ["0"]);
+    // Already released
+    assert_eq!(describe(buffer.push(update(0, 0, "a"))), Vec::<String>::new());
+
+    assert_eq!(describe(buffer.push(update(2, 1, "c"))), Vec::<String>::new());
+    // Still waiting, and a different update with the same display order loses to the first
+    assert_eq!(describe(buffer.push(update(2, 1, "c"))), Vec::<String>::new());
+    assert_eq!(describe(buffer.push(update(2, 1, "not c"))), Vec::<String>::new());
+
+    let released = buffer.push(update(1, 2, "b"));
+    let texts: Vec<_> = released.iter()
+        .map(|released| match released {
+            Released::Update(item) => { item.data.display_text.as_str() }
+            Released::Gap(_) => { panic!("Unexpected gap") }
+        })
+        .collect();
+    assert_eq!(texts, ["b", "c"]);
+}
+
+#[test]
+fn gives_up_on_a_gap_after_the_window() {
+    let mut buffer = ReorderBuffer::new(Duration::seconds(60));
+    assert_eq!(describe(buffer.push(update(0, 0, "a"))), ["0"]);
+    assert_eq!(describe(buffer.push(update(3, 10, "d"))), Vec::<String>::new());
+    assert_eq!(describe(buffer.push(update(4, 69, "e"))), Vec::<String>::new());
+    // Chronicler time is now a full window past the first update waiting on the gap
+    assert_eq!(describe(buffer.push(update(5, 70, "f"))), ["gap 1..3", "3", "4", "5"]);
+    // The updates given up on are dropped if they turn up after all
+    assert_eq!(describe(buffer.push(update(1, 71, "b"))), Vec::<String>::new());
+}
+
+#[test]
+fn flush_releases_everything_left() {
+    let mut buffer = ReorderBuffer::new(Duration::seconds(60));
+    assert_eq!(describe(buffer.push(update(0, 0, "a"))), ["0"]);
+    assert_eq!(describe(buffer.push(update(2, 1, "c"))), Vec::<String>::new());
+    assert_eq!(describe(buffer.push(update(5, 2, "f"))), Vec::<String>::new());
+    assert_eq!(describe(buffer.flush()), ["gap 1..2", "2", "gap 3..5", "5"]);
+    assert_eq!(describe(buffer.flush()), Vec::<String>::new());
+}
+
+#[test]
+fn window_survives_a_checkpoint() {
+    let mut buffer = ReorderBuffer::new(Duration::seconds(60));
+    assert_eq!(describe(buffer.push(update(0, 0, "a"))), ["0"]);
+    assert_eq!(describe(buffer.push(update(2, 0, "c"))), Vec::<String>::new());
+
+    let saved = serde_json::to_string(&buffer).unwrap();
+    let mut buffer = serde_json::from_str::<ReorderBuffer>(&saved).unwrap()
+        .with_window(Duration::seconds(60));
+    assert_eq!(describe(buffer.push(update(3, 30, "d"))), Vec::<String>::new());
+    assert_eq!(describe(buffer.push(update(1, 31, "b"))), ["1", "2", "3"]);
+}
@@ -0,0 +1,58 @@
+use fed2::chron_schema::{GameEventsResponse, SchemaMode};
+
+const PAGE: &str = r#"{"items": [{"game_id": "11111111-1111-1111-1111-111111111111", "timestamp": "2023-01-01T00:00:00Z", "season": 3, "data": {"changedState": {"started": true, "weather": 7}, "displayDelay": 5, "displayOrder": 0, "displayText": "Play Ball!", "displayTime": "2023-01-01T00:00:00Z", "isPostseason": false}}], "next_page": null, "total": 1}"#;
+
+#[test]
+fn tolerant_mode_keeps_unknown_fields() {
+    let page: GameEventsResponse = serde_json::from_str(PAGE).unwrap();
+    page.check_unknown_fields(SchemaMode::Tolerant).unwrap();
+
+    let item = &page.items[0];
+    assert_eq!(page.extra["total"], 1);
+    assert_eq!(item.extra["season"], 3);
+    assert_eq!(item.data.extra["isPostseason"], false);
+    assert_eq!(item.data.changed_state.extra["weather"], 7);
+    assert_eq!(item.data.changed_state.started, Some(true));
+}
+
+#[test]
+fn strict_mode_rejects_unknown_fields() {
+    let page: GameEventsResponse = serde_json::from_str(PAGE).unwrap();
+    let err = page.check_unknown_fields(SchemaMode::Strict).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Chronicler sent fields Fed doesn't know about: total, items.season, items.data.isPostseason, items.data.changedState.weather",
+    );
+}
+
+/// `PAGE` with a batter and a baserunner, each with a field Fed doesn't know about
+fn page_with_players() -> GameEventsResponse {
+    let page = PAGE.replace(
+        r#""started": true"#,
+        r#""batter": {"id": "22222222-2222-2222-2222-222222222222", "name": "Bat Ter", "mods": []}, "baserunners": [{"id": "33333333-3333-3333-3333-333333333333", "name": "Run Ner", "base": 1, "speed": 2}]"#,
+    );
+    serde_json::from_str(&page).unwrap()
+}
+
+#[test]
+fn tolerant_mode_keeps_unknown_player_fields() {
+    let page = page_with_players();
+    page.check_unknown_fields(SchemaMode::Tolerant).unwrap();
+
+    let changed_state = &page.items[0].data.changed_state;
+    let batter = changed_state.batter.as_ref().unwrap().as_ref().unwrap();
+    assert_eq!(batter.name, "Bat Ter");
+    assert_eq!(batter.extra["mods"], serde_json::json!([]));
+    assert_eq!(changed_state.baserunners.as_ref().unwrap()[0].extra["speed"], 2);
+    // They aren't written back out with the player
+    assert_eq!(serde_json::to_value(batter).unwrap(), serde_json::json!({ "id": "22222222-2222-2222-2222-222222222222", "name": "Bat Ter" }));
+}
+
+#[test]
+fn strict_mode_rejects_unknown_player_fields() {
+    let err = page_with_players().check_unknown_fields(SchemaMode::Strict).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Chronicler sent fields Fed doesn't know about: total, items.season, items.data.isPostseason, items.data.changedState.weather, items.data.changedState.batter.mods, items.data.changedState.baserunners.speed",
+    );
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use fed2::fed_schema::{Event, FedEvent};
+use fed2::store::Store;
+use uuid::Uuid;
+use common::{game, GAME};
+
+fn sequences(events: &[FedEvent]) -> Vec<u64> {
+    events.iter().map(|event| event.sequence).collect()
+}
+
+#[test]
+fn ingest_is_idempotent_and_queryable() {
+    let mut store = Store::in_memory().unwrap();
+    store.ingest(&game()).unwrap();
+    store.ingest(&game()).unwrap();
+
+    let stored = store.game_events(GAME).unwrap();
+    assert_eq!(sequences(&stored), [0, 1, 2, 3, 4, 5, 6]);
+    assert!(matches!(stored[6].event, Event::HomeRun { .. }));
+
+    assert_eq!(sequences(&store.plate_appearances(Uuid::from_u128(1)).unwrap()), [3]);
+    assert_eq!(sequences(&store.plate_appearances(Uuid::from_u128(2)).unwrap()), [6]);
+    assert_eq!(sequences(&store.plate_appearances(Uuid::from_u128(3)).unwrap()), [] as [u64; 0]);
+
+    // The walker is also the runner who scores on the home run
+    assert_eq!(sequences(&store.player_events(Uuid::from_u128(1)).unwrap()), [1, 3, 6]);
+    assert_eq!(sequences(&store.player_events(Uuid::from_u128(3)).unwrap()), [1, 4]);
+    assert_eq!(sequences(&store.events_of_type("home_run").unwrap()), [6]);
+
+    assert_eq!(store.player_name(Uuid::from_u128(2)).unwrap().as_deref(), Some("Slugger"));
+    assert_eq!(store.player_name(Uuid::from_u128(4)).unwrap(), None);
+}
+
+#[test]
+fn ingesting_a_reparsed_game_replaces_it() {
+    let mut store = Store::in_memory().unwrap();
+    store.ingest(&game()).unwrap();
+
+    // Parsed again from updates that start later, so every event gets a new id, and without the
+    // last two events
+    let mut reparsed = game();
+    reparsed.truncate(5);
+    for event in &mut reparsed {
+        event.first_display_order += 100;
+        event.id = FedEvent::id_for(GAME, event.first_display_order);
+    }
+    store.ingest(&reparsed).unwrap();
+
+    let stored = store.game_events(GAME).unwrap();
+    assert_eq!(sequences(&stored), [0, 1, 2, 3, 4]);
+    assert!(stored.iter().zip(&reparsed).all(|(stored, reparsed)| stored.id == reparsed.id));
+    assert_eq!(sequences(&store.events_of_type("home_run").unwrap()), [] as [u64; 0]);
+    // The slugger's only remaining event is coming up to bat
+    assert_eq!(sequences(&store.player_events(Uuid::from_u128(2)).unwrap()), [4]);
+}
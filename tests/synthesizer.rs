@@ -0,0 +1,92 @@
+mod common;
+
+use chrono::{TimeZone, Utc};
+use fed2::chron_schema::{PlayerDesc, RunnerDesc};
+use fed2::fed_schema::*;
+use fed2::parser::Parser;
+use fed2::synthesizer::{synthesize_game, Rosters};
+use common::player;
+
+/// Contact with a sound effect, which chronicler always gives a location
+fn contact(batter: &PlayerDesc, location: FieldLocation) -> Contact {
+    Contact {
+        batter: batter.clone(),
+        location: Some(location),
+        flavor: ContactFlavor::NamedWithSound { sound_effect: SoundEffect::Crack, verb: ContactVerb::Hits },
+    }
+}
+
+/// Half an inning with one of most kinds of event
+fn events() -> Vec<Event> {
+    let pitcher = player(1, "Pitch Er");
+    let [walker, strikeout, flyout, slugger, singler, chooser] =
+        [10, 11, 12, 13, 14, 15].map(|n| player(n, &format!("Batter {n}")));
+    let [left_fielder, shortstop] = [player(20, "Left Fielder"), player(21, "Short Stop")];
+    let batter_up = |batter: &PlayerDesc| Event::BatterUp { batter: batter.clone(), pitcher: Some(pitcher.clone()) };
+
+    vec![
+        Event::PlayBall,
+        batter_up(&walker),
+        Event::Ball { flavor: BallFlavor::Adjective(PitchAdjective::WellLocated) },
+        Event::Strike { flavor: StrikeFlavor::Looking },
+        Event::Foul { flavor: FoulFlavor::FoulBall },
+        Event::Ball { flavor: BallFlavor::Adjective(PitchAdjective::Auspicious) },
+        Event::Ball { flavor: BallFlavor::Adjective(PitchAdjective::Revolting) },
+        Event::Walk { batter: walker.clone(), flavor: WalkFlavor::DrawsWalk },
+        batter_up(&strikeout),
+        Event::Strike { flavor: StrikeFlavor::Swinging },
+        Event::Strike { flavor: StrikeFlavor::Looking },
+        Event::Strikeout { batter: strikeout, flavor: StrikeoutFlavor::NamedBatter },
+        batter_up(&flyout),
+        Event::Flyout { contact: contact(&flyout, FieldLocation::LeftField), defender: left_fielder.clone(), flavor: FlyoutFlavor::FlyOutTo },
+        batter_up(&slugger),
+        Event::HomeRun { contact: contact(&slugger, FieldLocation::DeepLeftField), scores: vec![walker] },
+        batter_up(&singler),
+        Event::Hit {
+            contact: contact(&singler, FieldLocation::LeftField),
+            fielding: MaybeFailedFielding::Fielding(Fielding { defender: left_fielder, flavor: FieldingFlavor::CollectsIt }),
+            hit_type: HitType::Single,
+            flavor: HitFlavor::Hits,
+            advancements: vec![],
+            scores: vec![],
+        },
+        batter_up(&chooser),
+        Event::FieldersChoice {
+            contact: contact(&chooser, FieldLocation::Infield),
+            fielding: Fielding { defender: shortstop, flavor: FieldingFlavor::ScoopsIt },
+            runner_out: RunnerDesc { id: singler.id, name: singler.name.clone(), base: 1, extra: Default::default() },
+        },
+        Event::EndOfHalfInning { top_of_inning: true, inning: 0 },
+    ]
+}
+
+/// Parses the updates synthesized for `events`
+fn read_back(events: &[Event]) -> Vec<Event> {
+    let rosters = Rosters {
+        home_defenders: vec![player(20, "Left Fielder"), player(21, "Short Stop")],
+        away_defenders: vec![player(30, "Away Fielder")],
+    };
+    let updates = synthesize_game(events, rosters, Utc.timestamp_opt(1_600_000_000, 0).unwrap()).unwrap();
+
+    let mut parser = Parser::default();
+    let mut parsed = Vec::new();
+    for update in updates {
+        let text = update.display_text.clone();
+        let (event, _) = parser.parse(update).unwrap_or_else(|err| panic!("Couldn't parse {text:?}: {err}"));
+        parsed.extend(event);
+    }
+    parsed
+}
+
+#[test]
+fn parser_reads_back_synthesized_games() {
+    let parsed = read_back(&events());
+    assert_eq!(serde_json::to_value(&parsed).unwrap(), serde_json::to_value(events()).unwrap());
+}
+
+#[test]
+fn batter_up_without_a_pitcher_is_read_back() {
+    let events = [Event::PlayBall, Event::BatterUp { batter: player(10, "Batter 10"), pitcher: None }];
+    let parsed = read_back(&events);
+    assert_eq!(serde_json::to_value(&parsed).unwrap(), serde_json::to_value(&events).unwrap());
+}
@@ -0,0 +1,95 @@
+mod common;
+
+use arrow_array::cast::AsArray;
+use arrow_schema::DataType;
+use fed2::{csv_export, parquet_export};
+use fed2::tables::flatten;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use common::{game, GAME};
+
+#[test]
+fn flattens_pitches_plate_appearances_and_baserunning() {
+    let tables = flatten(&game()).unwrap();
+
+    let pitches: Vec<_> = tables.pitches.iter()
+        .map(|row| (row.balls, row.strikes, row.result, row.flavor.as_deref()))
+        .collect();
+    assert_eq!(pitches, [
+        (0, 0, "ball", Some("adjective:well_located")),
+        (1, 0, "walk", Some("draws_walk")),
+        (0, 0, "strike", Some("looking")),
+        (0, 1, "in_play", None),
+    ]);
+
+    let plate_appearances: Vec<_> = tables.plate_appearances.iter()
+        .map(|row| (row.batter_name.as_str(), row.pitcher_name.as_deref(), row.outcome, row.location.as_deref(), row.runs_scored))
+        .collect();
+    assert_eq!(plate_appearances, [
+        ("Walker", Some("Pitcher"), "walk", None, 0.),
+        ("Slugger", Some("Pitcher"), "home_run", Some("deep_left_field"), 2.),
+    ]);
+
+    let baserunning: Vec<_> = tables.baserunning.iter()
+        .map(|row| (row.runner_name.as_str(), row.from_base, row.to_base, row.outcome, row.event_type))
+        .collect();
+    assert_eq!(baserunning, [
+        ("Walker", 0, Some(1), "reached", "walk"),
+        ("Walker", 1, None, "scored", "home_run"),
+        ("Slugger", 0, None, "scored", "home_run"),
+    ]);
+}
+
+#[test]
+fn incomplete_game_is_rejected() {
+    let mut events = game();
+    events.remove(0);
+    assert!(flatten(&events).is_err());
+}
+
+#[test]
+fn parquet_round_trip() {
+    let dir = std::env::temp_dir().join(format!("fed2-parquet-{}", std::process::id()));
+    parquet_export::write_game(&dir, GAME, &flatten(&game()).unwrap()).unwrap();
+
+    let file = std::fs::File::open(dir.join("pitches").join(format!("{GAME}.parquet"))).unwrap();
+    let batches = ParquetRecordBatchReaderBuilder::try_new(file).unwrap().build().unwrap()
+        .collect::<Result<Vec<_>, _>>().unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(batches.iter().map(|batch| batch.num_rows()).sum::<usize>(), 4);
+    let result = batches[0].column_by_name("result").unwrap();
+    assert_eq!(result.data_type(), &DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)));
+    let result = result.as_dictionary::<arrow_array::types::Int32Type>();
+    let values = result.values().as_string::<i32>();
+    let results: Vec<_> = result.keys().iter().map(|key| values.value(key.unwrap() as usize)).collect();
+    assert_eq!(results, ["ball", "walk", "strike", "in_play"]);
+}
+
+#[test]
+fn csv_columns_match_rows() {
+    let tables = flatten(&game()).unwrap();
+
+    /// The header csv would write from the row's field names
+    fn derived_header<T: serde::Serialize>(row: &T) -> String {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.serialize(row).unwrap();
+        let written = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+        written.lines().next().unwrap().to_string()
+    }
+    assert_eq!(derived_header(&tables.pitches[0]), csv_export::PITCH_COLUMNS.join(","));
+    assert_eq!(derived_header(&tables.plate_appearances[0]), csv_export::PLATE_APPEARANCE_COLUMNS.join(","));
+    assert_eq!(derived_header(&tables.baserunning[0]), csv_export::BASERUNNING_COLUMNS.join(","));
+
+    let mut written = Vec::new();
+    csv_export::write_plate_appearances(&mut written, &tables.plate_appearances).unwrap();
+    let written = String::from_utf8(written).unwrap();
+    let lines: Vec<_> = written.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0], csv_export::PLATE_APPEARANCE_COLUMNS.join(","));
+    assert!(lines[2].contains(",Slugger,") && lines[2].contains(",home_run,,deep_left_field,"));
+
+    // Empty tables still get a header
+    let mut written = Vec::new();
+    csv_export::write_pitches(&mut written, &[]).unwrap();
+    assert_eq!(String::from_utf8(written).unwrap().trim_end(), csv_export::PITCH_COLUMNS.join(","));
+}
@@ -0,0 +1,11 @@
+use fed2::fed_schema::typescript_definitions;
+
+#[test]
+fn checked_in_typescript_is_up_to_date() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/bindings/fed.d.ts");
+    let checked_in = std::fs::read_to_string(path).unwrap();
+    assert!(
+        checked_in == typescript_definitions(),
+        "bindings/fed.d.ts is stale. Regenerate it with `cargo run -- typescript > bindings/fed.d.ts`",
+    );
+}
@@ -0,0 +1,175 @@
+mod common;
+
+use fed2::chron_schema::{PlayerDesc, RunnerDesc, State};
+use fed2::fed_schema::*;
+use fed2::validator::{validate, Discrepancy};
+use common::player;
+
+fn runner(player: &PlayerDesc, base: i64) -> RunnerDesc {
+    RunnerDesc { id: player.id, name: player.name.clone(), base, extra: Default::default() }
+}
+
+fn contact(batter: &PlayerDesc) -> Contact {
+    Contact {
+        batter: batter.clone(),
+        location: None,
+        flavor: ContactFlavor::NamedWithSound { sound_effect: SoundEffect::Wham, verb: ContactVerb::Hits },
+    }
+}
+
+fn fielding() -> Fielding {
+    Fielding { defender: player(9, "Fielder"), flavor: FieldingFlavor::CollectsIt }
+}
+
+/// The top of the second, with `baserunners` on
+fn state(baserunners: Vec<RunnerDesc>) -> State {
+    State { inning: 2, baserunners, ..State::default() }
+}
+
+/// The fields `validate` found discrepancies in
+fn fields(event: &Event, before: &State, after: &State) -> Vec<&'static str> {
+    validate(event, before, after).into_iter().map(|discrepancy| discrepancy.field).collect()
+}
+
+#[test]
+fn walk_with_a_runner_on_first_puts_the_batter_on_first() {
+    let batter = player(1, "Batter");
+    let on_first = player(2, "On First");
+    let walk = Event::Walk { batter: batter.clone(), flavor: WalkFlavor::DrawsWalk };
+    let before = state(vec![runner(&on_first, 1)]);
+
+    let after = state(vec![runner(&on_first, 2), runner(&batter, 1)]);
+    assert_eq!(validate(&walk, &before, &after), []);
+
+    let after = state(vec![runner(&on_first, 3), runner(&batter, 2)]);
+    assert_eq!(validate(&walk, &before, &after), [Discrepancy {
+        field: "baserunner",
+        expected: "Batter on base 1".to_string(),
+        actual: "Batter on base 2".to_string(),
+    }]);
+}
+
+#[test]
+fn walk_with_the_bases_loaded_scores_a_run() {
+    let batter = player(1, "Batter");
+    let walk = Event::Walk { batter: batter.clone(), flavor: WalkFlavor::DrawsWalk };
+    let loaded = (1..=3).map(|base| runner(&player(1 + base as u128, "Runner"), base)).collect();
+    let before = state(loaded);
+
+    let mut after = state(vec![runner(&batter, 1)]);
+    after.away_score = 1.;
+    assert_eq!(validate(&walk, &before, &after), []);
+
+    after.away_score = 0.;
+    assert_eq!(fields(&walk, &before, &after), ["batting team's score"]);
+}
+
+#[test]
+fn hit_moves_runners_to_their_bases() {
+    let batter = player(1, "Batter");
+    let on_first = player(2, "On First");
+    let on_second = player(3, "On Second");
+    let double = Event::Hit {
+        contact: contact(&batter),
+        fielding: MaybeFailedFielding::Fielding(fielding()),
+        hit_type: HitType::Double,
+        flavor: HitFlavor::Hits,
+        advancements: vec![Advancement { runner: on_first.clone(), to_base: Base::Third }],
+        scores: vec![on_second.clone()],
+    };
+    let before = state(vec![runner(&on_first, 1), runner(&on_second, 2)]);
+
+    let mut after = state(vec![runner(&on_first, 3), runner(&batter, 2)]);
+    after.away_score = 1.;
+    assert_eq!(validate(&double, &before, &after), []);
+
+    let mut after = state(vec![runner(&on_first, 2), runner(&on_second, 3), runner(&batter, 1)]);
+    after.away_score = 0.;
+    assert_eq!(fields(&double, &before, &after), ["batting team's score", "baserunner", "baserunner", "baserunner"]);
+}
+
+#[test]
+fn fielders_choice_puts_the_batter_on_first() {
+    let batter = player(1, "Batter");
+    let on_first = player(2, "On First");
+    let fielders_choice = Event::FieldersChoice {
+        contact: contact(&batter),
+        fielding: fielding(),
+        runner_out: runner(&on_first, 1),
+    };
+    let before = state(vec![runner(&on_first, 1)]);
+
+    let mut after = state(vec![runner(&batter, 1)]);
+    after.outs = 1;
+    assert_eq!(validate(&fielders_choice, &before, &after), []);
+
+    let mut after = state(vec![runner(&on_first, 2), runner(&batter, 1)]);
+    after.outs = 1;
+    assert_eq!(fields(&fielders_choice, &before, &after), ["baserunner"]);
+}
+
+#[test]
+fn count_must_follow_the_pitch() {
+    let ball = Event::Ball { flavor: BallFlavor::DoesntBlink };
+    let strike = Event::Strike { flavor: StrikeFlavor::Looking };
+    let foul = Event::Foul { flavor: FoulFlavor::FoulBall };
+    let before = State { balls: 1, strikes: 2, ..state(vec![]) };
+
+    assert_eq!(fields(&ball, &before, &State { balls: 2, ..before.clone() }), [] as [&str; 0]);
+    assert_eq!(fields(&ball, &before, &before), ["balls"]);
+    assert_eq!(fields(&strike, &before, &State { strikes: 3, ..before.clone() }), [] as [&str; 0]);
+    assert_eq!(fields(&strike, &before, &State { balls: 2, ..before.clone() }), ["balls", "strikes"]);
+    // A foul with two strikes leaves the count alone
+    assert_eq!(fields(&foul, &before, &before), [] as [&str; 0]);
+    assert_eq!(fields(&foul, &before, &State { strikes: 3, ..before.clone() }), ["strikes"]);
+}
+
+#[test]
+fn plate_appearance_must_reset_the_count_and_add_outs() {
+    let strikeout = Event::Strikeout { batter: player(1, "Batter"), flavor: StrikeoutFlavor::NamedBatter };
+    let before = State { balls: 3, strikes: 2, outs: 1, ..state(vec![]) };
+
+    assert_eq!(fields(&strikeout, &before, &State { balls: 0, strikes: 0, outs: 2, ..before.clone() }), [] as [&str; 0]);
+    assert_eq!(fields(&strikeout, &before, &State { outs: 1, ..before.clone() }), ["balls", "strikes", "outs"]);
+}
+
+#[test]
+fn home_run_must_score_everyone() {
+    let batter = player(1, "Batter");
+    let on_third = player(2, "On Third");
+    let before = state(vec![runner(&on_third, 3)]);
+    let home_run = |scores| Event::HomeRun { contact: contact(&batter), scores };
+
+    let after = State { away_score: 2., ..state(vec![]) };
+    assert_eq!(fields(&home_run(vec![on_third.clone()]), &before, &after), [] as [&str; 0]);
+    // The runner on third is missing from `scores`
+    assert_eq!(fields(&home_run(vec![]), &before, &after), ["runners scoring"]);
+    // Only the batter scored, and the runner is still on third
+    let after = State { away_score: 1., ..before.clone() };
+    assert_eq!(fields(&home_run(vec![on_third.clone()]), &before, &after), ["batting team's score", "baserunners"]);
+}
+
+#[test]
+fn score_and_inning_only_change_when_they_should() {
+    let ball = Event::Ball { flavor: BallFlavor::DoesntBlink };
+    let before = State { balls: 0, ..state(vec![runner(&player(2, "Runner"), 2)]) };
+    let after = State { balls: 1, ..before.clone() };
+
+    assert_eq!(fields(&ball, &before, &State { home_score: 1., ..after.clone() }), ["fielding team's score"]);
+    assert_eq!(fields(&ball, &before, &State { away_score: 1., ..after.clone() }), ["batting team's score"]);
+    assert_eq!(fields(&ball, &before, &State { inning: 3, top_of_inning: false, ..after.clone() }), ["inning", "top_of_inning"]);
+    assert_eq!(fields(&ball, &before, &State { baserunners: vec![runner(&player(2, "Runner"), 3)], ..after.clone() }), ["baserunners"]);
+}
+
+#[test]
+fn end_of_half_inning_moves_to_the_next_half() {
+    let before = State { outs: 3, ..state(vec![runner(&player(2, "Runner"), 2)]) };
+    let after = State { outs: 0, top_of_inning: false, ..state(vec![]) };
+
+    let end = Event::EndOfHalfInning { top_of_inning: true, inning: 2 };
+    assert_eq!(fields(&end, &before, &after), [] as [&str; 0]);
+    assert_eq!(fields(&end, &before, &before), ["top_of_inning", "outs", "baserunners"]);
+
+    let wrong_inning = Event::EndOfHalfInning { top_of_inning: false, inning: 1 };
+    assert_eq!(fields(&wrong_inning, &before, &after), ["ending half-inning's top_of_inning", "ending half-inning's inning"]);
+}